@@ -0,0 +1,39 @@
+CREATE TABLE IF NOT EXISTS players (
+    id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    player_name VARCHAR(255) NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS tutorial_levels (
+    id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    descrip TEXT NOT NULL,
+    fen TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tutorial_level_solutions (
+    id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    player_id INT NOT NULL,
+    level_id INT NOT NULL,
+    number_of_steps INT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS challenge_prefabs (
+    id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    fen TEXT NOT NULL,
+    level_name VARCHAR(255) NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS challenge_solutions (
+    id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    fen TEXT NOT NULL,
+    num_of_steps INT NOT NULL,
+    player_id INT NOT NULL,
+    prefab_id INT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS multiplayer_solutions (
+    id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    fen TEXT NOT NULL,
+    num_of_steps INT NOT NULL,
+    player_id INT NOT NULL,
+    prefab_id INT NOT NULL
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS hint_usage (
+    id INT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    player_id INT NOT NULL,
+    level_id INT NOT NULL,
+    game_mode VARCHAR(32) NOT NULL,
+    fen TEXT NOT NULL,
+    script_length BIGINT UNSIGNED NOT NULL
+);
+
+-- Last and in one statement, so the migration can run again when it failed before
+ALTER TABLE challenge_solutions
+    ADD COLUMN seed BIGINT UNSIGNED NULL,
+    ADD COLUMN difficulty CHAR(1) NULL,
+    ADD COLUMN optimal_steps INT UNSIGNED NULL;
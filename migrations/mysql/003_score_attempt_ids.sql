@@ -0,0 +1,23 @@
+-- Replayed scores carry the id of their attempt, so a write that reached the
+-- database before the connection dropped is not saved twice. Older rows keep NULL.
+-- Every ALTER commits on its own, so each one is skipped when its column exists
+-- and the migration can run again after it failed halfway.
+SET @add_column = (SELECT IF(COUNT(*) = 0,
+    'ALTER TABLE challenge_solutions ADD COLUMN attempt_id BIGINT UNSIGNED NULL UNIQUE',
+    'DO 0')
+    FROM information_schema.COLUMNS
+    WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'challenge_solutions'
+    AND COLUMN_NAME = 'attempt_id');
+PREPARE add_column FROM @add_column;
+EXECUTE add_column;
+DEALLOCATE PREPARE add_column;
+
+SET @add_column = (SELECT IF(COUNT(*) = 0,
+    'ALTER TABLE multiplayer_solutions ADD COLUMN attempt_id BIGINT UNSIGNED NULL UNIQUE',
+    'DO 0')
+    FROM information_schema.COLUMNS
+    WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = 'multiplayer_solutions'
+    AND COLUMN_NAME = 'attempt_id');
+PREPARE add_column FROM @add_column;
+EXECUTE add_column;
+DEALLOCATE PREPARE add_column;
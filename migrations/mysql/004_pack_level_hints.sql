@@ -0,0 +1,5 @@
+-- Pack levels are not in the database, their hints keep the id of the pack level
+-- instead of a level id
+ALTER TABLE hint_usage
+    MODIFY COLUMN level_id INT NULL,
+    ADD COLUMN pack_level VARCHAR(255) NULL;
@@ -0,0 +1,39 @@
+CREATE TABLE IF NOT EXISTS players (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    player_name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS tutorial_levels (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    descrip TEXT NOT NULL,
+    fen TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tutorial_level_solutions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    player_id INTEGER NOT NULL,
+    level_id INTEGER NOT NULL,
+    number_of_steps INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS challenge_prefabs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    fen TEXT NOT NULL,
+    level_name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS challenge_solutions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    fen TEXT NOT NULL,
+    num_of_steps INTEGER NOT NULL,
+    player_id INTEGER NOT NULL,
+    prefab_id INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS multiplayer_solutions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    fen TEXT NOT NULL,
+    num_of_steps INTEGER NOT NULL,
+    player_id INTEGER NOT NULL,
+    prefab_id INTEGER NOT NULL
+);
@@ -0,0 +1,12 @@
+ALTER TABLE challenge_solutions ADD COLUMN seed INTEGER;
+ALTER TABLE challenge_solutions ADD COLUMN difficulty TEXT;
+ALTER TABLE challenge_solutions ADD COLUMN optimal_steps INTEGER;
+
+CREATE TABLE IF NOT EXISTS hint_usage (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    player_id INTEGER NOT NULL,
+    level_id INTEGER NOT NULL,
+    game_mode TEXT NOT NULL,
+    fen TEXT NOT NULL,
+    script_length INTEGER NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Replayed scores carry the id of their attempt, so a write that reached the
+-- database before the connection dropped is not saved twice. Older rows keep NULL.
+ALTER TABLE challenge_solutions ADD COLUMN attempt_id INTEGER;
+CREATE UNIQUE INDEX challenge_solutions_attempt_id ON challenge_solutions (attempt_id);
+ALTER TABLE multiplayer_solutions ADD COLUMN attempt_id INTEGER;
+CREATE UNIQUE INDEX multiplayer_solutions_attempt_id ON multiplayer_solutions (attempt_id);
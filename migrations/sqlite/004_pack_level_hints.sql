@@ -0,0 +1,15 @@
+-- Pack levels are not in the database, their hints keep the id of the pack level
+-- instead of a level id. SQLite can not drop NOT NULL, so the table is rebuilt.
+CREATE TABLE hint_usage_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    player_id INTEGER NOT NULL,
+    level_id INTEGER,
+    pack_level TEXT,
+    game_mode TEXT NOT NULL,
+    fen TEXT NOT NULL,
+    script_length INTEGER NOT NULL
+);
+INSERT INTO hint_usage_new (id, player_id, level_id, game_mode, fen, script_length)
+SELECT id, player_id, level_id, game_mode, fen, script_length FROM hint_usage;
+DROP TABLE hint_usage;
+ALTER TABLE hint_usage_new RENAME TO hint_usage;
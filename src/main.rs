@@ -1,4 +1,4 @@
-use std::env;
+use std::{env, process};
 
 use bevy::prelude::*;
 
@@ -6,12 +6,16 @@ mod model;
 mod utilities;
 mod view;
 
-use utilities::database_plugin::DatabasePlugin;
+use utilities::database_plugin::{migrate_database, DatabasePlugin};
 use utilities::language_plugin::LanguagePlugin;
+use utilities::level_pack_plugin::LevelPackPlugin;
 use utilities::network_plugin::NetworkPlugin;
+use utilities::score_queue_plugin::ScoreQueuePlugin;
 use view::game_view::game_view_plugin::GameViewPlugin;
 use view::image_handler::ImageHandlerPlugin;
+use view::level_editor_view::level_editor_plugin::LevelEditorPlugin;
 use view::level_selector_view::level_selector_plugin::LevelSelectorPlugin;
+use view::loading_spinner::LoadingSpinnerPlugin;
 use view::main_menu::main_menu_plugin::MainMenuPlugin;
 use view::multiplayer_view::multiplayer_view_plugin::MultiplayerViewPlugin;
 use view::scoreboard_view::scoreboard_plugin::ScoreboardPlugin;
@@ -21,12 +25,24 @@ const MAX_LEVEL_WIDTH: f32 = 500.0;
 const MAX_LEVEL_HEIGHT: f32 = 550.0;
 const SHIFT_TO_RIGHT: f32 = 7.0;
 const SHIFT_DOWN: f32 = 20.0;
+/// `cargo run -- --migrate` sets up the database of the config and exits
+const MIGRATE_FLAG: &str = "--migrate";
 
 #[derive(Component)]
 pub struct MainCamera;
 
 fn main() {
     env::set_var("RUST_BACKTRACE", "1");
+    if env::args().any(|arg| arg == MIGRATE_FLAG) {
+        match migrate_database() {
+            Ok(()) => println!("Database schema is up to date"),
+            Err(err) => {
+                eprintln!("Database could not be migrated: {err}");
+                process::exit(1);
+            }
+        }
+        return;
+    }
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             window: WindowDescriptor {
@@ -40,11 +56,15 @@ fn main() {
         .add_plugin(DatabasePlugin)
         .add_plugin(ImageHandlerPlugin)
         .add_plugin(LanguagePlugin)
+        .add_plugin(LevelPackPlugin)
+        .add_plugin(ScoreQueuePlugin)
         .add_plugin(GameViewPlugin)
         .add_plugin(LevelSelectorPlugin)
         .add_plugin(MainMenuPlugin)
         .add_plugin(ScoreboardPlugin)
         .add_plugin(MultiplayerViewPlugin)
+        .add_plugin(LevelEditorPlugin)
+        .add_plugin(LoadingSpinnerPlugin)
         .add_plugin(NetworkPlugin)
         .run();
 }
@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+
+use super::{
+    fen::pawn_token,
+    generator::{PAWN_SPOT, PERL_SPOT},
+    simulation::PawnColor,
+    tiles::{EMPTY_LETTER, PERL_LETTER, TILES},
+};
+
+pub const MAX_DRAFT_SIZE: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DraftCell {
+    Tile(char),
+    Pawn(PawnColor),
+    /// Prefab cell that may become a perl
+    PerlSpot,
+    /// Prefab cell that may become a pawn
+    PawnSpot,
+}
+
+impl DraftCell {
+    pub const EMPTY: DraftCell = DraftCell::Tile(EMPTY_LETTER);
+}
+
+/// What the level editor is building: a finished level, or a challenge prefab
+/// that the generator fills with perls and pawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DraftKind {
+    #[default]
+    Level,
+    Prefab,
+}
+
+/// Level being built in the level editor. It is kept as plain cells, so a board
+/// that does not parse yet can still be shown and edited.
+#[derive(Resource, Debug, Clone)]
+pub struct LevelDraft {
+    pub kind: DraftKind,
+    pub rows: u32,
+    pub columns: u32,
+    /// Perls to collect, only used by levels
+    pub goal: u32,
+    /// Pawns the generator places, only used by prefabs
+    pub pawns: u32,
+    cells: Vec<DraftCell>,
+}
+
+impl Default for LevelDraft {
+    fn default() -> Self {
+        LevelDraft {
+            kind: DraftKind::Level,
+            rows: 6,
+            columns: 6,
+            goal: 1,
+            pawns: 1,
+            cells: vec![DraftCell::EMPTY; 36],
+        }
+    }
+}
+
+impl LevelDraft {
+    pub fn cell(&self, row: u32, column: u32) -> DraftCell {
+        self.cells[(row * self.columns + column) as usize]
+    }
+
+    /// Every pawn exists once, painting it somewhere else moves it.
+    pub fn paint(&mut self, row: u32, column: u32, cell: DraftCell) {
+        if let DraftCell::Pawn(_) = cell {
+            for other in self.cells.iter_mut() {
+                if *other == cell {
+                    *other = DraftCell::EMPTY;
+                }
+            }
+        }
+        let index = (row * self.columns + column) as usize;
+        self.cells[index] = cell;
+    }
+
+    /// Keeps the cells that still fit, new cells are empty.
+    pub fn resize(&mut self, rows: u32, columns: u32) {
+        let rows = rows.clamp(1, MAX_DRAFT_SIZE);
+        let columns = columns.clamp(1, MAX_DRAFT_SIZE);
+        let mut cells = vec![DraftCell::EMPTY; (rows * columns) as usize];
+        for row in 0..rows.min(self.rows) {
+            for column in 0..columns.min(self.columns) {
+                cells[(row * columns + column) as usize] = self.cell(row, column);
+            }
+        }
+        self.rows = rows;
+        self.columns = columns;
+        self.cells = cells;
+    }
+
+    /// FEN of a level, or the prefab string of a prefab, see `generator::fill_prefab`.
+    pub fn to_fen(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        for row in 0..self.rows {
+            let mut line = String::new();
+            let mut empty_run: u32 = 0;
+            for column in 0..self.columns {
+                let cell = self.cell(row, column);
+                if cell == DraftCell::EMPTY {
+                    empty_run += 1;
+                    continue;
+                }
+                if empty_run > 0 {
+                    line.push_str(&empty_run.to_string());
+                    empty_run = 0;
+                }
+                match cell {
+                    DraftCell::Tile(letter) => line.push(letter),
+                    DraftCell::Pawn(color) => line.push_str(&pawn_token(color)),
+                    DraftCell::PerlSpot => line.push(PERL_SPOT),
+                    DraftCell::PawnSpot => line.push(PAWN_SPOT),
+                }
+            }
+            if empty_run > 0 {
+                line.push_str(&empty_run.to_string());
+            }
+            lines.push(line);
+        }
+        let last_field = match self.kind {
+            DraftKind::Level => self.goal,
+            DraftKind::Prefab => self.pawns,
+        };
+        format!(
+            "{} {} {} {}",
+            self.rows,
+            self.columns,
+            lines.join("/"),
+            last_field
+        )
+    }
+
+    /// Level with every perl spot filled and pawns on the first pawn spots,
+    /// the fullest board a prefab can produce.
+    pub fn fullest_fen(&self) -> String {
+        let mut draft = self.clone();
+        draft.kind = DraftKind::Level;
+        draft.goal = 0;
+        let mut pawns = PawnColor::ALL.iter().take(self.pawns as usize);
+        for cell in draft.cells.iter_mut() {
+            match *cell {
+                DraftCell::PerlSpot => {
+                    *cell = DraftCell::Tile(PERL_LETTER);
+                    draft.goal += 1;
+                }
+                DraftCell::PawnSpot => {
+                    *cell = match pawns.next() {
+                        Some(color) => DraftCell::Pawn(*color),
+                        None => DraftCell::EMPTY,
+                    }
+                }
+                _ => {}
+            }
+        }
+        draft.to_fen()
+    }
+
+    pub fn count(&self, cell: DraftCell) -> usize {
+        self.cells.iter().filter(|other| **other == cell).count()
+    }
+
+    /// Switching to a prefab turns pawns into pawn spots, switching back
+    /// turns perl spots into perls and drops the pawn spots.
+    pub fn set_kind(&mut self, kind: DraftKind) {
+        if self.kind == kind {
+            return;
+        }
+        if kind == DraftKind::Prefab {
+            self.pawns = (self.count_pawns() as u32).max(1);
+        }
+        for cell in self.cells.iter_mut() {
+            *cell = match (kind, *cell) {
+                (DraftKind::Prefab, DraftCell::Pawn(_)) => DraftCell::PawnSpot,
+                (DraftKind::Level, DraftCell::PerlSpot) => DraftCell::Tile(PERL_LETTER),
+                (DraftKind::Level, DraftCell::PawnSpot) => DraftCell::EMPTY,
+                (_, cell) => cell,
+            };
+        }
+        self.kind = kind;
+    }
+
+    fn count_pawns(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|cell| matches!(cell, DraftCell::Pawn(_)))
+            .count()
+    }
+
+    /// Palette of the editor: every tile with a picture, then every pawn,
+    /// or the two spots for prefabs.
+    pub fn palette(kind: DraftKind) -> Vec<DraftCell> {
+        let mut palette = vec![DraftCell::EMPTY];
+        for tile in TILES.iter() {
+            if tile.kind.sprite().is_some() {
+                palette.push(DraftCell::Tile(tile.letter));
+            }
+        }
+        match kind {
+            DraftKind::Level => {
+                for color in PawnColor::ALL {
+                    palette.push(DraftCell::Pawn(color));
+                }
+            }
+            DraftKind::Prefab => {
+                palette.push(DraftCell::PerlSpot);
+                palette.push(DraftCell::PawnSpot);
+            }
+        }
+        palette
+    }
+}
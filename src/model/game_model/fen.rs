@@ -0,0 +1,513 @@
+use std::fmt;
+
+use simple_matrix::Matrix;
+
+use super::{
+    game::{cell_size, Game, GameCompleted, GameMode, LevelCell},
+    simulation::{PawnColor, PawnRule},
+    tiles::{tile_spec, TileBehavior, EMPTY_LETTER},
+};
+use crate::view::game_view::game_view_plugin::RedrawPuzzle;
+
+/// Reasons a FEN string cannot be turned into a level.
+/// Rows and columns are counted from 0, messages show them counted from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    BadHeader {
+        field: &'static str,
+        value: String,
+    },
+    WrongRowCount {
+        expected: u32,
+        found: u32,
+    },
+    RowWidthMismatch {
+        row: u32,
+        expected: u32,
+        found: u32,
+    },
+    UnknownTile {
+        row: u32,
+        column: u32,
+        letter: char,
+    },
+    BadPawn {
+        row: u32,
+        column: u32,
+        token: String,
+    },
+    DuplicatePawn {
+        row: u32,
+        column: u32,
+        id: usize,
+    },
+    MissingPawn,
+    GoalTooHigh {
+        goal: u32,
+        available: u32,
+    },
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::BadHeader { field, value } => {
+                write!(f, "bad header: {field} is \"{value}\"")
+            }
+            FenError::WrongRowCount { expected, found } => {
+                write!(f, "expected {expected} rows, found {found}")
+            }
+            FenError::RowWidthMismatch {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {}: expected {expected} cells, found {found}",
+                row + 1
+            ),
+            FenError::UnknownTile {
+                row,
+                column,
+                letter,
+            } => write!(
+                f,
+                "row {}, column {}: unknown tile '{letter}'",
+                row + 1,
+                column + 1
+            ),
+            FenError::BadPawn { row, column, token } => write!(
+                f,
+                "row {}, column {}: bad pawn \"{token}\", expected [0] to [{}]",
+                row + 1,
+                column + 1,
+                PawnColor::ALL.len() - 1
+            ),
+            FenError::DuplicatePawn { row, column, id } => write!(
+                f,
+                "row {}, column {}: pawn {id} appears more than once",
+                row + 1,
+                column + 1
+            ),
+            FenError::MissingPawn => write!(f, "there is no pawn on the board"),
+            FenError::GoalTooHigh { goal, available } => write!(
+                f,
+                "goal is {goal} perls, but only {available} are on the board"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+fn parse_header_field(field: &'static str, value: Option<&str>) -> Result<u32, FenError> {
+    let value = value.unwrap_or_default();
+    match value.parse::<u32>() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(FenError::BadHeader {
+            field,
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// Letters of the first pawns, every other pawn is written by its id as "[n]".
+pub const PAWN_LETTERS: [char; 2] = ['p', 'P'];
+
+pub fn pawn_token(color: PawnColor) -> String {
+    match PAWN_LETTERS.get(color.id()) {
+        Some(letter) => letter.to_string(),
+        None => format!("[{}]", color.id()),
+    }
+}
+
+impl Game {
+    pub fn try_from_fen(fen: &str) -> Result<Game, FenError> {
+        let mut iter = fen.split_whitespace();
+        let num_of_rows = parse_header_field("rows", iter.next())?;
+        let num_of_columns = parse_header_field("columns", iter.next())?;
+        let board = iter.next().ok_or(FenError::BadHeader {
+            field: "board",
+            value: String::new(),
+        })?;
+        let goal = parse_header_field("goal", iter.next())?;
+        let pawn_rule = match iter.next() {
+            Some(name) => PawnRule::from_name(name).ok_or(FenError::BadHeader {
+                field: "pawn rule",
+                value: name.to_string(),
+            })?,
+            None => PawnRule::default(),
+        };
+        if num_of_rows == 0 {
+            return Err(FenError::BadHeader {
+                field: "rows",
+                value: num_of_rows.to_string(),
+            });
+        }
+        if num_of_columns == 0 {
+            return Err(FenError::BadHeader {
+                field: "columns",
+                value: num_of_columns.to_string(),
+            });
+        }
+
+        let lines: Vec<&str> = board.split('/').collect();
+        if lines.len() as u32 != num_of_rows {
+            return Err(FenError::WrongRowCount {
+                expected: num_of_rows,
+                found: lines.len() as u32,
+            });
+        }
+
+        let mut matrix: Matrix<LevelCell> =
+            Matrix::new(num_of_rows as usize, num_of_columns as usize);
+        let image_size = cell_size(num_of_rows, num_of_columns);
+        let mut pawns: Vec<PawnColor> = Vec::new();
+        let mut perls = 0;
+        for (i, line) in lines.iter().enumerate() {
+            let row = i as u32;
+            let mut col_counter: u32 = 0;
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                let cells: Vec<LevelCell> = if let Some(digit) = c.to_digit(10) {
+                    // Consecutive digits form one run, so "12" is twelve empty cells
+                    let mut run = digit;
+                    while let Some(next) = chars.peek().and_then(|next| next.to_digit(10)) {
+                        run = run.saturating_mul(10).saturating_add(next);
+                        chars.next();
+                    }
+                    if col_counter.saturating_add(run) > num_of_columns {
+                        return Err(FenError::RowWidthMismatch {
+                            row,
+                            expected: num_of_columns,
+                            found: col_counter.saturating_add(run),
+                        });
+                    }
+                    let empty = tile_spec(EMPTY_LETTER).unwrap().level_cell(image_size);
+                    vec![empty; run as usize]
+                } else if c == '[' {
+                    let mut token = String::from("[");
+                    for next in chars.by_ref() {
+                        token.push(next);
+                        if next == ']' {
+                            break;
+                        }
+                    }
+                    let color = token
+                        .strip_prefix('[')
+                        .and_then(|token| token.strip_suffix(']'))
+                        .and_then(|id| id.parse::<usize>().ok())
+                        .and_then(PawnColor::from_id);
+                    let color = match color {
+                        Some(color) => color,
+                        None => {
+                            return Err(FenError::BadPawn {
+                                row,
+                                column: col_counter,
+                                token,
+                            })
+                        }
+                    };
+                    let mut cell = tile_spec(PAWN_LETTERS[0]).unwrap().level_cell(image_size);
+                    cell.pawn = Some(color);
+                    vec![cell]
+                } else if let Some(tile) = tile_spec(c) {
+                    let mut cell = tile.level_cell(image_size);
+                    if tile.behavior() == TileBehavior::Pawn {
+                        let id = PAWN_LETTERS.iter().position(|letter| *letter == c).unwrap();
+                        cell.pawn = PawnColor::from_id(id);
+                    }
+                    vec![cell]
+                } else {
+                    return Err(FenError::UnknownTile {
+                        row,
+                        column: col_counter,
+                        letter: c,
+                    });
+                };
+                for cell in cells {
+                    if let Some(color) = cell.pawn {
+                        if pawns.contains(&color) {
+                            return Err(FenError::DuplicatePawn {
+                                row,
+                                column: col_counter,
+                                id: color.id(),
+                            });
+                        }
+                        pawns.push(color);
+                    }
+                    if matches!(
+                        cell.spec().behavior(),
+                        TileBehavior::Pearl | TileBehavior::Shell { .. }
+                    ) {
+                        perls += 1;
+                    }
+                    if col_counter < num_of_columns {
+                        matrix.set(i, col_counter as usize, cell);
+                    }
+                    col_counter += 1;
+                }
+            }
+            if col_counter != num_of_columns {
+                return Err(FenError::RowWidthMismatch {
+                    row,
+                    expected: num_of_columns,
+                    found: col_counter,
+                });
+            }
+        }
+        if pawns.is_empty() {
+            return Err(FenError::MissingPawn);
+        }
+        if goal > perls {
+            return Err(FenError::GoalTooHigh {
+                goal,
+                available: perls,
+            });
+        }
+
+        Ok(Game {
+            level_id: 0,
+            pack_level: None,
+            level_matrix: matrix,
+            fen: fen.to_string(),
+            rows: num_of_rows,
+            columns: num_of_columns,
+            collected_perls: 0,
+            required_perls: goal,
+            puzzle: Vec::new(),
+            redraw_cond: RedrawPuzzle::No,
+            selected_puzzle_piece: -1,
+            game_completed: GameCompleted::No,
+            solution_steps: 0,
+            solution: 0,
+            game_mode: GameMode::Tutorial,
+            pawn_rule,
+            par: None,
+            seed: None,
+            hint: None,
+        })
+    }
+}
+
+impl Game {
+    /// Writes the level back as FEN text, run-length encoding empty cells.
+    /// The pawn rule is only written when it is not the default.
+    pub fn to_fen(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        for i in 0..self.rows as usize {
+            let mut line = String::new();
+            let mut empty_run: u32 = 0;
+            for j in 0..self.columns as usize {
+                let cell = self.level_matrix.get(i, j).unwrap();
+                if cell.letter == EMPTY_LETTER {
+                    empty_run += 1;
+                    continue;
+                }
+                push_empty_run(&mut line, empty_run);
+                empty_run = 0;
+                match cell.pawn {
+                    Some(color) => line.push_str(&pawn_token(color)),
+                    None => line.push(cell.letter),
+                }
+            }
+            push_empty_run(&mut line, empty_run);
+            lines.push(line);
+        }
+        let mut fen = format!(
+            "{} {} {} {}",
+            self.rows,
+            self.columns,
+            lines.join("/"),
+            self.required_perls
+        );
+        if self.pawn_rule != PawnRule::default() {
+            fen.push(' ');
+            fen.push_str(self.pawn_rule.name());
+        }
+        fen
+    }
+}
+
+fn push_empty_run(line: &mut String, empty_run: u32) {
+    if empty_run > 0 {
+        line.push_str(&empty_run.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::game_model::tiles::TILES;
+
+    fn cells(game: &Game) -> Vec<(char, Option<PawnColor>)> {
+        let mut cells = Vec::new();
+        for i in 0..game.rows as usize {
+            for j in 0..game.columns as usize {
+                let cell = game.level_matrix.get(i, j).unwrap();
+                cells.push((cell.letter, cell.pawn));
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn round_trip_keeps_every_tile() {
+        let mut row: String = TILES.iter().map(|tile| tile.letter).collect();
+        row.push_str("[2]");
+        let columns = TILES.len() + 1;
+        let fen = format!("2 {columns} {row}/{columns} 1 swap");
+
+        let game = Game::try_from_fen(&fen).unwrap();
+        let written = game.to_fen();
+        let reparsed = Game::try_from_fen(&written).unwrap();
+
+        assert_eq!(cells(&game), cells(&reparsed));
+        assert_eq!(written, reparsed.to_fen());
+        assert_eq!(reparsed.pawn_rule, PawnRule::Swap);
+        assert_eq!(reparsed.required_perls, 1);
+        for tile in TILES {
+            assert!(
+                cells(&reparsed)
+                    .iter()
+                    .any(|(letter, _)| *letter == tile.letter),
+                "tile '{}' was lost",
+                tile.letter
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_multi_digit_runs() {
+        let fen = "2 12 p11/12 0";
+        let game = Game::try_from_fen(fen).unwrap();
+        assert_eq!(game.to_fen(), fen);
+    }
+
+    fn error(fen: &str) -> FenError {
+        Game::try_from_fen(fen)
+            .err()
+            .unwrap_or_else(|| panic!("\"{fen}\" should not parse"))
+    }
+
+    #[test]
+    fn bad_header_names_the_field() {
+        for (fen, field, value) in [
+            ("x 2 p1 0", "rows", "x"),
+            ("0 2 p1 0", "rows", "0"),
+            ("1 0 p1 0", "columns", "0"),
+            ("1 2", "board", ""),
+            ("1 2 p1", "goal", ""),
+        ] {
+            assert_eq!(
+                error(fen),
+                FenError::BadHeader {
+                    field,
+                    value: value.to_string()
+                },
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_row_count_is_reported() {
+        assert_eq!(
+            error("3 2 p1/2 0"),
+            FenError::WrongRowCount {
+                expected: 3,
+                found: 2
+            }
+        );
+    }
+
+    #[test]
+    fn row_width_mismatch_is_reported() {
+        assert_eq!(
+            error("2 2 p1/3 0"),
+            FenError::RowWidthMismatch {
+                row: 1,
+                expected: 2,
+                found: 3
+            }
+        );
+        assert_eq!(
+            error("1 3 p1 0"),
+            FenError::RowWidthMismatch {
+                row: 0,
+                expected: 3,
+                found: 2
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_tile_is_reported() {
+        assert_eq!(
+            error("1 3 p1? 0"),
+            FenError::UnknownTile {
+                row: 0,
+                column: 2,
+                letter: '?'
+            }
+        );
+    }
+
+    #[test]
+    fn bad_pawn_is_reported() {
+        let too_high = format!("[{}]", PawnColor::ALL.len());
+        for token in [too_high.as_str(), "[x]", "[1"] {
+            assert_eq!(
+                error(&format!("1 2 p{token} 0")),
+                FenError::BadPawn {
+                    row: 0,
+                    column: 1,
+                    token: token.to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn duplicate_pawn_is_reported() {
+        assert_eq!(
+            error("2 2 p1/1[0] 0"),
+            FenError::DuplicatePawn {
+                row: 1,
+                column: 1,
+                id: 0
+            }
+        );
+    }
+
+    #[test]
+    fn missing_pawn_is_reported() {
+        assert_eq!(error("1 2 C1 1"), FenError::MissingPawn);
+    }
+
+    #[test]
+    fn goal_too_high_is_reported() {
+        assert_eq!(
+            error("1 3 pCo 3"),
+            FenError::GoalTooHigh {
+                goal: 3,
+                available: 2
+            }
+        );
+    }
+
+    #[test]
+    fn pawn_rule_is_optional() {
+        let rule = |fen| Game::try_from_fen(fen).ok().map(|game| game.pawn_rule);
+        assert_eq!(rule("1 2 p1 0"), Some(PawnRule::Block));
+        assert_eq!(rule("1 2 p1 0 push"), Some(PawnRule::Push));
+        assert_eq!(rule("1 2 p1 0 swap"), Some(PawnRule::Swap));
+        assert_eq!(
+            error("1 2 p1 0 shove"),
+            FenError::BadHeader {
+                field: "pawn rule",
+                value: "shove".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    fen::pawn_token,
+    game::Game,
+    simulation::{Instruction, PawnColor, Simulation},
+    solver::{candidate_instructions, solve, Solution},
+    tiles::{TileBehavior, EMPTY_LETTER, PERL_LETTER},
+};
+
+/// States the solver may visit while checking one generated board.
+const GENERATOR_STATE_LIMIT: usize = 50_000;
+/// Fresh boards tried for a prefab before the closest one is handed out, or
+/// none when not a single one could be solved.
+const MAX_ATTEMPTS: u32 = 20;
+
+/// Prefab cell that may become a perl. Prefabs write empty cells as digits
+/// only, so this never means an empty cell there.
+pub const PERL_SPOT: char = '_';
+/// Prefab cell that may become a pawn.
+pub const PAWN_SPOT: char = '+';
+/// Written at the front of every challenge code. Anything that changes which
+/// board a seed gives, like the random generator or how prefabs are filled,
+/// has to bump it so old codes are rejected instead of opening another board.
+pub const CODE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+/// Tuning knobs for filling a prefab. Every time a cell gets a perl or a pawn
+/// the chance for the next one drops by `drift`, and it grows by `drift` every
+/// time a cell is left empty.
+struct Knobs {
+    perl_probability: f64,
+    pawn_probability: f64,
+    drift: f64,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn code(&self) -> char {
+        match self {
+            Difficulty::Easy => 'E',
+            Difficulty::Medium => 'M',
+            Difficulty::Hard => 'H',
+        }
+    }
+
+    pub fn from_code(code: char) -> Option<Difficulty> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.code() == code.to_ascii_uppercase())
+    }
+
+    fn knobs(&self) -> Knobs {
+        match self {
+            Difficulty::Easy => Knobs {
+                perl_probability: 0.3,
+                pawn_probability: 0.4,
+                drift: 0.15,
+            },
+            Difficulty::Medium => Knobs {
+                perl_probability: 0.5,
+                pawn_probability: 0.4,
+                drift: 0.1,
+            },
+            Difficulty::Hard => Knobs {
+                perl_probability: 0.7,
+                pawn_probability: 0.4,
+                drift: 0.05,
+            },
+        }
+    }
+
+    /// Scores from `difficulty_score` a board of this difficulty should have.
+    fn score_range(&self) -> (f32, f32) {
+        match self {
+            Difficulty::Easy => (0.0, 12.0),
+            Difficulty::Medium => (12.0, 25.0),
+            Difficulty::Hard => (25.0, f32::MAX),
+        }
+    }
+
+    /// How far `score` is from the range of this difficulty, 0 inside it.
+    fn distance(&self, score: f32) -> f32 {
+        let (low, high) = self.score_range();
+        (low - score).max(score - high).max(0.0)
+    }
+}
+
+/// Everything needed to rebuild a generated board from its prefab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardSeed {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+}
+
+impl BoardSeed {
+    /// Seeds are kept to 32 bits so challenge codes stay short enough to type.
+    pub fn random(difficulty: Difficulty) -> BoardSeed {
+        BoardSeed {
+            seed: rand::thread_rng().gen::<u32>() as u64,
+            difficulty,
+        }
+    }
+}
+
+/// Short code that identifies one generated board, e.g. "1-12H-1Z141Z3"
+/// for prefab 12 on hard, made by version 1 of the generator.
+pub fn challenge_code(prefab_id: i32, board_seed: BoardSeed) -> String {
+    const DIGITS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut digits = Vec::new();
+    let mut rest = board_seed.seed;
+    loop {
+        digits.push(DIGITS[(rest % 36) as usize] as char);
+        rest /= 36;
+        if rest == 0 {
+            break;
+        }
+    }
+    let seed: String = digits.into_iter().rev().collect();
+    format!(
+        "{CODE_VERSION}-{prefab_id}{}-{seed}",
+        board_seed.difficulty.code()
+    )
+}
+
+/// Reads a code made by `challenge_code`, codes without a difficulty letter are medium.
+/// Codes of another generator version are not read, they would give another board.
+pub fn parse_challenge_code(code: &str) -> Option<(i32, BoardSeed)> {
+    let mut parts = code.trim().split('-');
+    let (version, prefab, seed) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || version.parse::<u32>().ok()? != CODE_VERSION {
+        return None;
+    }
+    let (prefab_id, difficulty) = match prefab.chars().last().and_then(Difficulty::from_code) {
+        Some(difficulty) => (&prefab[..prefab.len() - 1], difficulty),
+        None => (prefab, Difficulty::default()),
+    };
+    Some((
+        prefab_id.parse::<i32>().ok()?,
+        BoardSeed {
+            seed: u64::from_str_radix(seed, 36).ok()?,
+            difficulty,
+        },
+    ))
+}
+
+/// Rates a solvable board: longer solutions, more choices along the way and
+/// perls spread far apart all make a level harder.
+/// Easy boards score below 12, hard ones 25 and above.
+pub fn difficulty_score(game: &Game, solution: &[Instruction]) -> f32 {
+    // Average number of instructions that do something on the way to the goal
+    let mut simulation = Simulation::new(game);
+    let instructions = candidate_instructions(&simulation);
+    let mut choices = 0;
+    for instruction in solution {
+        let state = simulation.state().clone();
+        for candidate in instructions.iter() {
+            simulation.set_state(state.clone());
+            if simulation.step(*candidate).counts_as_step() {
+                choices += 1;
+            }
+        }
+        simulation.set_state(state);
+        simulation.step(*instruction);
+    }
+    let branching = choices as f32 / solution.len().max(1) as f32;
+
+    // Mean distance between two perls, relative to the size of the board
+    let mut perls = Vec::new();
+    for row in 0..game.rows as usize {
+        for column in 0..game.columns as usize {
+            let cell = game.level_matrix.get(row, column).unwrap();
+            if matches!(
+                cell.spec().behavior(),
+                TileBehavior::Pearl | TileBehavior::Shell { .. }
+            ) {
+                perls.push((row as f32, column as f32));
+            }
+        }
+    }
+    let mut distance = 0.0;
+    let mut pairs = 0;
+    for (i, a) in perls.iter().enumerate() {
+        for b in perls.iter().skip(i + 1) {
+            distance += (a.0 - b.0).abs() + (a.1 - b.1).abs();
+            pairs += 1;
+        }
+    }
+    let spread = if pairs > 0 {
+        distance / pairs as f32 / (game.rows + game.columns) as f32
+    } else {
+        0.0
+    };
+
+    solution.len() as f32 + branching + 10.0 * spread
+}
+
+/// Fills a challenge prefab and checks the board with the solver, so challenge
+/// mode only hands out levels that can be finished. When some perls cannot be
+/// reached the board is kept, but it only asks for the ones that can.
+/// Boards are filled until one scores inside the range of the difficulty,
+/// otherwise the closest one is used. Comes with the number of steps of the
+/// shortest solution, the par of the board. `None` when no board could be
+/// solved, the caller has to pick another prefab. The same prefab and seed
+/// always give the same board.
+pub fn generate_from_prefab(prefab: &str, board_seed: BoardSeed) -> Option<(String, u32)> {
+    let mut random = ChaCha8Rng::seed_from_u64(board_seed.seed);
+    let knobs = board_seed.difficulty.knobs();
+    let mut closest: Option<(f32, String, u32)> = None;
+    for _ in 0..MAX_ATTEMPTS {
+        let fen = fill_prefab(prefab, &knobs, &mut random);
+        let mut game = match Game::try_from_fen(&fen) {
+            Ok(game) => game,
+            Err(err) => {
+                warn!("Prefab \"{prefab}\" produced a broken level: {err}");
+                continue;
+            }
+        };
+        let solution = match solve(&game, GENERATOR_STATE_LIMIT) {
+            Solution::Solved(solution) if !solution.is_empty() => solution,
+            Solution::Unsolvable { most_perls } if most_perls > 0 => {
+                game.required_perls = most_perls;
+                match solve(&game, GENERATOR_STATE_LIMIT) {
+                    Solution::Solved(solution) => solution,
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        let distance = board_seed
+            .difficulty
+            .distance(difficulty_score(&game, &solution));
+        let steps = solution.len() as u32;
+        if distance <= 0.0 {
+            return Some((game.to_fen(), steps));
+        }
+        let is_closer = match &closest {
+            Some((best, ..)) => distance < *best,
+            None => true,
+        };
+        if is_closer {
+            closest = Some((distance, game.to_fen(), steps));
+        }
+    }
+    if closest.is_none() {
+        warn!("No solvable board found for prefab \"{prefab}\"");
+    }
+    closest.map(|(_, fen, steps)| (fen, steps))
+}
+
+/// How a prefab behaves over many boards, so it can be judged before it is published.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RollReport {
+    pub rolls: u32,
+    /// Boards where every placed perl can be collected
+    pub solvable: u32,
+    /// Boards where only some perls can be collected, the generator lowers their goal
+    pub partly_solvable: u32,
+    /// Solution lengths of the fully solvable boards, shortest first
+    pub steps: Vec<u32>,
+}
+
+impl RollReport {
+    /// Share of the boards, in percent, that can be finished as filled.
+    pub fn solvable_percent(&self) -> u32 {
+        self.solvable * 100 / self.rolls.max(1)
+    }
+
+    pub fn partly_solvable_percent(&self) -> u32 {
+        self.partly_solvable * 100 / self.rolls.max(1)
+    }
+
+    pub fn median_steps(&self) -> Option<u32> {
+        self.steps.get(self.steps.len() / 2).copied()
+    }
+}
+
+/// Fills the prefab `rolls` times with the knobs of `difficulty` and solves every
+/// board as it comes out, before the generator retries or lowers any goal.
+/// Seeds run from 0, so rolling the same prefab twice gives the same report.
+pub fn roll_prefab(prefab: &str, difficulty: Difficulty, rolls: u32) -> RollReport {
+    let knobs = difficulty.knobs();
+    let mut report = RollReport { rolls, ..default() };
+    for seed in 0..rolls as u64 {
+        let fen = fill_prefab(prefab, &knobs, &mut ChaCha8Rng::seed_from_u64(seed));
+        let game = match Game::try_from_fen(&fen) {
+            Ok(game) => game,
+            Err(_) => continue,
+        };
+        match solve(&game, GENERATOR_STATE_LIMIT) {
+            Solution::Solved(solution) if !solution.is_empty() => {
+                report.solvable += 1;
+                report.steps.push(solution.len() as u32);
+            }
+            Solution::Unsolvable { most_perls } if most_perls > 0 => {
+                report.partly_solvable += 1;
+            }
+            _ => {}
+        }
+    }
+    report.steps.sort_unstable();
+    report
+}
+
+/// Prefabs are FEN strings whose last field is the number of pawns instead of
+/// the goal, e.g. "3 5 Z+_+Z/Z3Z/ZZ_ZZ 1". `PERL_SPOT` may become a perl and
+/// `PAWN_SPOT` may become a pawn, the goal is the number of perls placed.
+/// Spots that stay unused become empty cells.
+fn fill_prefab(prefab: &str, knobs: &Knobs, random: &mut impl Rng) -> String {
+    let mut pawn_probability = knobs.pawn_probability;
+    let mut perl_probability = knobs.perl_probability;
+
+    let mut fen: String = String::new();
+    let mut pawns_added: usize = 0;
+    let mut perl_counter: i32 = 0;
+    let mut prefab_iter = prefab.split_whitespace();
+    fen.push_str(prefab_iter.next().unwrap_or_default());
+    fen.push(' ');
+    fen.push_str(prefab_iter.next().unwrap_or_default());
+    fen.push(' ');
+    let board = prefab_iter.next().unwrap_or_default();
+    let number_of_pawns: usize = prefab_iter
+        .next()
+        .and_then(|pawns| pawns.parse::<usize>().ok())
+        .unwrap_or(1)
+        .min(PawnColor::ALL.len());
+    let mut spawn_points_left = board.matches(PAWN_SPOT).count();
+    let mut updated_string = String::new();
+    for char in board.chars() {
+        if char == PERL_SPOT {
+            if random.gen_bool(perl_probability.clamp(0.0, 1.0)) {
+                updated_string.push(PERL_LETTER);
+                perl_probability -= knobs.drift;
+                perl_counter += 1;
+            } else {
+                updated_string.push(EMPTY_LETTER);
+                perl_probability += knobs.drift;
+            }
+        } else if char == PAWN_SPOT {
+            let pawns_missing = number_of_pawns.saturating_sub(pawns_added);
+            // Once there are only as many pawn spots left as pawns missing, every one of them gets a pawn
+            if pawns_missing > 0
+                && (spawn_points_left <= pawns_missing
+                    || random.gen_bool(pawn_probability.clamp(0.0, 1.0)))
+            {
+                updated_string.push_str(&pawn_token(PawnColor::ALL[pawns_added]));
+                pawns_added += 1;
+                pawn_probability -= knobs.drift;
+            } else {
+                updated_string.push(EMPTY_LETTER);
+                if pawns_missing > 0 {
+                    pawn_probability += knobs.drift;
+                }
+            }
+            spawn_points_left -= 1;
+        } else {
+            updated_string.push(char);
+        }
+    }
+    fen.push_str(updated_string.as_str());
+    fen.push(' ');
+    fen.push_str(perl_counter.to_string().as_str());
+    fen
+}
@@ -1,2 +1,8 @@
+pub mod draft;
+pub mod fen;
 pub mod game;
+pub mod generator;
 pub mod pizzle_pieces;
+pub mod simulation;
+pub mod solver;
+pub mod tiles;
@@ -33,3 +33,16 @@ impl Action for CollectPerlPuzzlePiece {
 }
 
 impl CollectPerlPuzzlePiece {}
+
+#[derive(Component)]
+pub struct OpenShellPuzzlePiece {
+    pub pawn_color: String,
+}
+
+impl Action for OpenShellPuzzlePiece {
+    fn get_action(&self) -> String {
+        format!("o{}s", self.pawn_color)
+    }
+}
+
+impl OpenShellPuzzlePiece {}
@@ -0,0 +1,838 @@
+use std::{fmt, str::FromStr};
+
+use bevy::prelude::*;
+
+use super::{
+    game::Game,
+    tiles::{TileBehavior, TileSpec},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn from_code(code: char) -> Option<Direction> {
+        match code {
+            'u' => Some(Direction::Up),
+            'd' => Some(Direction::Down),
+            'l' => Some(Direction::Left),
+            'r' => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> char {
+        match self {
+            Direction::Up => 'u',
+            Direction::Down => 'd',
+            Direction::Left => 'l',
+            Direction::Right => 'r',
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    pub fn counter_clockwise(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct GridPosition {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl GridPosition {
+    pub fn neighbour(
+        &self,
+        direction: Direction,
+        rows: usize,
+        columns: usize,
+    ) -> Option<GridPosition> {
+        match direction {
+            Direction::Up if self.row > 0 => Some(GridPosition {
+                row: self.row - 1,
+                column: self.column,
+            }),
+            Direction::Down if self.row + 1 < rows => Some(GridPosition {
+                row: self.row + 1,
+                column: self.column,
+            }),
+            Direction::Left if self.column > 0 => Some(GridPosition {
+                row: self.row,
+                column: self.column - 1,
+            }),
+            Direction::Right if self.column + 1 < columns => Some(GridPosition {
+                row: self.row,
+                column: self.column + 1,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Colour of a pawn doubles as its identity: the pawn with FEN id `n`
+/// always gets `PawnColor::ALL[n]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PawnColor {
+    Green,
+    Orange,
+    Blue,
+    Purple,
+    Red,
+    Yellow,
+}
+
+impl PawnColor {
+    pub const ALL: [PawnColor; 6] = [
+        PawnColor::Green,
+        PawnColor::Orange,
+        PawnColor::Blue,
+        PawnColor::Purple,
+        PawnColor::Red,
+        PawnColor::Yellow,
+    ];
+
+    pub fn from_id(id: usize) -> Option<PawnColor> {
+        PawnColor::ALL.get(id).copied()
+    }
+
+    pub fn id(&self) -> usize {
+        PawnColor::ALL
+            .iter()
+            .position(|color| color == self)
+            .unwrap()
+    }
+
+    pub fn from_code(code: char) -> Option<PawnColor> {
+        PawnColor::ALL
+            .into_iter()
+            .find(|color| color.code() == code)
+    }
+
+    pub fn code(&self) -> char {
+        match self {
+            PawnColor::Green => 'g',
+            PawnColor::Orange => 'o',
+            PawnColor::Blue => 'b',
+            PawnColor::Purple => 'p',
+            PawnColor::Red => 'r',
+            PawnColor::Yellow => 'y',
+        }
+    }
+
+    /// Green and orange have their own art, the rest tint the neutral pawn.
+    pub fn sprite(&self) -> &'static str {
+        match self {
+            PawnColor::Green => "pawns/green.png",
+            PawnColor::Orange => "pawns/orange.png",
+            _ => "pawns/neutral.png",
+        }
+    }
+
+    pub fn tint(&self) -> Color {
+        match self {
+            PawnColor::Green | PawnColor::Orange => Color::WHITE,
+            PawnColor::Blue => Color::rgb(0.4, 0.6, 1.0),
+            PawnColor::Purple => Color::rgb(0.75, 0.5, 1.0),
+            PawnColor::Red => Color::rgb(1.0, 0.4, 0.4),
+            PawnColor::Yellow => Color::rgb(1.0, 0.9, 0.3),
+        }
+    }
+}
+
+/// Pawn on the level view, `id` is its number in the FEN.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pawn {
+    pub id: usize,
+    pub color: PawnColor,
+}
+
+/// Shells open or close on their own every this many counted steps.
+pub const SHELL_CYCLE: u32 = 4;
+
+/// Single block of a script, decoded from the strings stored in `ScriptRes`
+/// ("mgd" moves the green pawn down, "cop" makes the orange pawn collect a perl,
+/// "ogs" makes the green pawn open the shell it stands on). Pawns are named by
+/// `PawnColor::code`. Palette buttons carry the instruction they add.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Move {
+        pawn: PawnColor,
+        direction: Direction,
+    },
+    CollectPerl {
+        pawn: PawnColor,
+    },
+    OpenShell {
+        pawn: PawnColor,
+    },
+}
+
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = s.chars().collect();
+        let instruction = match chars.as_slice() {
+            ['m', pawn, direction] => PawnColor::from_code(*pawn).and_then(|pawn| {
+                Direction::from_code(*direction)
+                    .map(|direction| Instruction::Move { pawn, direction })
+            }),
+            ['c', pawn, 'p'] => {
+                PawnColor::from_code(*pawn).map(|pawn| Instruction::CollectPerl { pawn })
+            }
+            ['o', pawn, 's'] => {
+                PawnColor::from_code(*pawn).map(|pawn| Instruction::OpenShell { pawn })
+            }
+            _ => None,
+        };
+        instruction.ok_or(format!("unknown instruction \"{s}\""))
+    }
+}
+
+/// What happens when a pawn walks into another pawn. Set per level by the
+/// optional fifth FEN field, levels without it use `Block`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PawnRule {
+    #[default]
+    Block,
+    Push,
+    Swap,
+}
+
+impl PawnRule {
+    pub fn from_name(name: &str) -> Option<PawnRule> {
+        match name {
+            "block" => Some(PawnRule::Block),
+            "push" => Some(PawnRule::Push),
+            "swap" => Some(PawnRule::Swap),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PawnRule::Block => "block",
+            PawnRule::Push => "push",
+            PawnRule::Swap => "swap",
+        }
+    }
+}
+
+/// Why a move did not happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockReason {
+    Edge,
+    Wall,
+    Stone,
+    Pawn(PawnColor),
+}
+
+impl fmt::Display for BlockReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockReason::Edge => write!(f, "the edge of the board"),
+            BlockReason::Wall => write!(f, "a wall"),
+            BlockReason::Stone => write!(f, "a stone that cannot move"),
+            BlockReason::Pawn(color) => write!(f, "the {color:?} pawn"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Moved,
+    Pushed,
+    PawnPushed,
+    Swapped,
+    Blocked(BlockReason),
+    PerlCollected,
+    NothingToCollect,
+    ShellClosed,
+    ShellOpened,
+    NoShell,
+    NoSuchPawn,
+}
+
+impl StepOutcome {
+    pub fn counts_as_step(&self) -> bool {
+        matches!(
+            self,
+            StepOutcome::Moved
+                | StepOutcome::Pushed
+                | StepOutcome::PawnPushed
+                | StepOutcome::Swapped
+                | StepOutcome::PerlCollected
+                | StepOutcome::NothingToCollect
+                | StepOutcome::ShellClosed
+                | StepOutcome::ShellOpened
+                | StepOutcome::NoShell
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PawnState {
+    pub color: PawnColor,
+    pub position: GridPosition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PerlState {
+    pub position: GridPosition,
+    pub collected: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StoneState {
+    pub origin: GridPosition,
+    pub position: GridPosition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShellState {
+    pub position: GridPosition,
+    pub open: bool,
+    pub pearl: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct SimulationState {
+    pub pawns: Vec<PawnState>,
+    pub perls: Vec<PerlState>,
+    pub stones: Vec<StoneState>,
+    pub shells: Vec<ShellState>,
+    pub collected_perls: u32,
+    pub steps: u32,
+}
+
+/// Game rules on the level grid, independent of the UI.
+/// Level view only mirrors the state kept here.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Simulation {
+    rows: usize,
+    columns: usize,
+    pawn_rule: PawnRule,
+    colliders: Vec<Option<&'static TileSpec>>,
+    initial: SimulationState,
+    state: SimulationState,
+    history: Vec<SimulationState>,
+}
+
+impl Simulation {
+    pub fn new(game: &Game) -> Simulation {
+        let rows = game.rows as usize;
+        let columns = game.columns as usize;
+        let mut colliders = vec![None; rows * columns];
+        let mut state = SimulationState::default();
+        for row in 0..rows {
+            for column in 0..columns {
+                let cell = game.level_matrix.get(row, column).unwrap();
+                let position = GridPosition { row, column };
+                match cell.spec().behavior() {
+                    TileBehavior::Empty => {}
+                    TileBehavior::Pawn => state.pawns.push(PawnState {
+                        color: cell.pawn.expect("Pawn cells carry their colour"),
+                        position,
+                    }),
+                    TileBehavior::Pearl => state.perls.push(PerlState {
+                        position,
+                        collected: false,
+                    }),
+                    TileBehavior::Movable => state.stones.push(StoneState {
+                        origin: position,
+                        position,
+                    }),
+                    TileBehavior::Shell { open } => state.shells.push(ShellState {
+                        position,
+                        open,
+                        pearl: true,
+                    }),
+                    TileBehavior::Collider => colliders[row * columns + column] = Some(cell.spec()),
+                }
+            }
+        }
+        Simulation {
+            rows,
+            columns,
+            pawn_rule: game.pawn_rule,
+            colliders,
+            initial: state.clone(),
+            state,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn collected_perls(&self) -> u32 {
+        self.state.collected_perls
+    }
+
+    pub fn state(&self) -> &SimulationState {
+        &self.state
+    }
+
+    /// Jumps to `state`, forgetting how the simulation got here.
+    pub fn set_state(&mut self, state: SimulationState) {
+        self.state = state;
+        self.history.clear();
+    }
+
+    pub fn steps(&self) -> u32 {
+        self.state.steps
+    }
+
+    /// Pawns present on the level, ordered by their FEN id.
+    pub fn pawn_colors(&self) -> Vec<PawnColor> {
+        let mut colors: Vec<PawnColor> = self.state.pawns.iter().map(|p| p.color).collect();
+        colors.sort_by_key(|color| color.id());
+        colors
+    }
+
+    pub fn has_shells(&self) -> bool {
+        !self.state.shells.is_empty()
+    }
+
+    pub fn pawn_position(&self, pawn: PawnColor) -> Option<GridPosition> {
+        self.state
+            .pawns
+            .iter()
+            .find(|p| p.color == pawn)
+            .map(|p| p.position)
+    }
+
+    pub fn is_perl_collected(&self, position: GridPosition) -> bool {
+        self.state
+            .perls
+            .iter()
+            .any(|perl| perl.position == position && perl.collected)
+    }
+
+    pub fn stone_position(&self, origin: GridPosition) -> Option<GridPosition> {
+        self.state
+            .stones
+            .iter()
+            .find(|stone| stone.origin == origin)
+            .map(|stone| stone.position)
+    }
+
+    pub fn is_shell_open(&self, position: GridPosition) -> bool {
+        self.state
+            .shells
+            .iter()
+            .any(|shell| shell.position == position && shell.open)
+    }
+
+    pub fn shell_has_pearl(&self, position: GridPosition) -> bool {
+        self.state
+            .shells
+            .iter()
+            .any(|shell| shell.position == position && shell.pearl)
+    }
+
+    /// Applies one instruction. Every call can be undone with `step_back`,
+    /// including the ones that did not change anything.
+    pub fn step(&mut self, instruction: Instruction) -> StepOutcome {
+        self.history.push(self.state.clone());
+        let outcome = match instruction {
+            Instruction::Move { pawn, direction } => self.move_pawn(pawn, direction),
+            Instruction::CollectPerl { pawn } => self.collect_perl(pawn),
+            Instruction::OpenShell { pawn } => self.open_shell(pawn),
+        };
+        if outcome.counts_as_step() {
+            self.state.steps += 1;
+            if self.state.steps % SHELL_CYCLE == 0 {
+                for shell in self.state.shells.iter_mut() {
+                    shell.open = !shell.open;
+                }
+            }
+        }
+        outcome
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        if let Some(previous) = self.history.pop() {
+            self.state = previous;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.state = self.initial.clone();
+        self.history.clear();
+    }
+
+    fn move_pawn(&mut self, pawn: PawnColor, direction: Direction) -> StepOutcome {
+        let index = match self.state.pawns.iter().position(|p| p.color == pawn) {
+            Some(index) => index,
+            None => return StepOutcome::NoSuchPawn,
+        };
+        let from = self.state.pawns[index].position;
+        let target = match from.neighbour(direction, self.rows, self.columns) {
+            Some(target) => target,
+            None => return StepOutcome::Blocked(BlockReason::Edge),
+        };
+        if !self.can_cross(from, target, direction) {
+            return StepOutcome::Blocked(BlockReason::Wall);
+        }
+        if let Some(other) = self.pawn_at(target) {
+            return self.meet_pawn(index, other, direction);
+        }
+        let outcome = match self.stone_at(target) {
+            Some(stone) => {
+                // Stones are pushed one cell, only onto bare floor
+                let beyond = match target.neighbour(direction, self.rows, self.columns) {
+                    Some(beyond) if self.is_floor(beyond) => beyond,
+                    _ => return StepOutcome::Blocked(BlockReason::Stone),
+                };
+                self.state.stones[stone].position = beyond;
+                StepOutcome::Pushed
+            }
+            None => StepOutcome::Moved,
+        };
+        self.state.pawns[index].position = target;
+        outcome
+    }
+
+    /// Pawn `index` walks into pawn `other`, what happens depends on the level's `PawnRule`.
+    fn meet_pawn(&mut self, index: usize, other: usize, direction: Direction) -> StepOutcome {
+        let from = self.state.pawns[index].position;
+        let target = self.state.pawns[other].position;
+        let blocked = StepOutcome::Blocked(BlockReason::Pawn(self.state.pawns[other].color));
+        match self.pawn_rule {
+            PawnRule::Block => blocked,
+            PawnRule::Push => {
+                // A pushed pawn moves one cell like a walking one, but never pushes further
+                let beyond = match target.neighbour(direction, self.rows, self.columns) {
+                    Some(beyond)
+                        if self.can_cross(target, beyond, direction)
+                            && self.pawn_at(beyond).is_none()
+                            && self.stone_at(beyond).is_none() =>
+                    {
+                        beyond
+                    }
+                    _ => return blocked,
+                };
+                self.state.pawns[other].position = beyond;
+                self.state.pawns[index].position = target;
+                StepOutcome::PawnPushed
+            }
+            PawnRule::Swap => {
+                self.state.pawns[other].position = from;
+                self.state.pawns[index].position = target;
+                StepOutcome::Swapped
+            }
+        }
+    }
+
+    fn collect_perl(&mut self, pawn: PawnColor) -> StepOutcome {
+        let position = match self.pawn_position(pawn) {
+            Some(position) => position,
+            None => return StepOutcome::NoSuchPawn,
+        };
+        if let Some(perl) = self
+            .state
+            .perls
+            .iter_mut()
+            .find(|perl| perl.position == position && !perl.collected)
+        {
+            perl.collected = true;
+            self.state.collected_perls += 1;
+            return StepOutcome::PerlCollected;
+        }
+        match self
+            .state
+            .shells
+            .iter_mut()
+            .find(|shell| shell.position == position && shell.pearl)
+        {
+            Some(shell) if shell.open => {
+                shell.pearl = false;
+                self.state.collected_perls += 1;
+                StepOutcome::PerlCollected
+            }
+            Some(_) => StepOutcome::ShellClosed,
+            None => StepOutcome::NothingToCollect,
+        }
+    }
+
+    fn open_shell(&mut self, pawn: PawnColor) -> StepOutcome {
+        let position = match self.pawn_position(pawn) {
+            Some(position) => position,
+            None => return StepOutcome::NoSuchPawn,
+        };
+        match self
+            .state
+            .shells
+            .iter_mut()
+            .find(|shell| shell.position == position)
+        {
+            Some(shell) => {
+                shell.open = true;
+                StepOutcome::ShellOpened
+            }
+            None => StepOutcome::NoShell,
+        }
+    }
+
+    fn collider_at(&self, position: GridPosition) -> Option<&'static TileSpec> {
+        self.colliders[position.row * self.columns + position.column]
+    }
+
+    /// Moving between neighbouring cells is blocked when either cell is solid
+    /// on the edge they share.
+    fn can_cross(&self, from: GridPosition, to: GridPosition, direction: Direction) -> bool {
+        let blocked_out = matches!(self.collider_at(from), Some(tile) if tile.is_solid(direction));
+        let blocked_in =
+            matches!(self.collider_at(to), Some(tile) if tile.is_solid(direction.opposite()));
+        !blocked_out && !blocked_in
+    }
+
+    fn pawn_at(&self, position: GridPosition) -> Option<usize> {
+        self.state
+            .pawns
+            .iter()
+            .position(|pawn| pawn.position == position)
+    }
+
+    fn stone_at(&self, position: GridPosition) -> Option<usize> {
+        self.state
+            .stones
+            .iter()
+            .position(|stone| stone.position == position)
+    }
+
+    /// Cell with nothing on it that a stone could cover.
+    fn is_floor(&self, position: GridPosition) -> bool {
+        self.collider_at(position).is_none()
+            && self.stone_at(position).is_none()
+            && self.pawn_at(position).is_none()
+            && !self
+                .state
+                .perls
+                .iter()
+                .any(|perl| perl.position == position && !perl.collected)
+            && !self
+                .state
+                .shells
+                .iter()
+                .any(|shell| shell.position == position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulation(fen: &str) -> Simulation {
+        Simulation::new(&Game::try_from_fen(fen).unwrap())
+    }
+
+    fn move_green(direction: Direction) -> Instruction {
+        Instruction::Move {
+            pawn: PawnColor::Green,
+            direction,
+        }
+    }
+
+    const COLLECT_GREEN: Instruction = Instruction::CollectPerl {
+        pawn: PawnColor::Green,
+    };
+
+    #[test]
+    fn step_back_restores_the_state_before_the_step() {
+        let mut simulation = simulation("2 3 p1C/3 1");
+        let start = simulation.state().clone();
+
+        assert_eq!(
+            simulation.step(move_green(Direction::Right)),
+            StepOutcome::Moved
+        );
+        let moved = simulation.state().clone();
+        assert_eq!(
+            simulation.step(move_green(Direction::Up)),
+            StepOutcome::Blocked(BlockReason::Edge)
+        );
+
+        assert!(simulation.step_back());
+        assert_eq!(simulation.state(), &moved);
+        assert!(simulation.step_back());
+        assert_eq!(simulation.state(), &start);
+        assert!(!simulation.step_back());
+    }
+
+    #[test]
+    fn reset_clears_steps_and_collected_perls() {
+        let mut simulation = simulation("2 3 p1C/3 1");
+        simulation.step(move_green(Direction::Right));
+        simulation.step(move_green(Direction::Right));
+        assert_eq!(simulation.step(COLLECT_GREEN), StepOutcome::PerlCollected);
+        assert_eq!(simulation.steps(), 3);
+        assert_eq!(simulation.collected_perls(), 1);
+
+        simulation.reset();
+
+        assert_eq!(simulation.steps(), 0);
+        assert_eq!(simulation.collected_perls(), 0);
+        assert_eq!(
+            simulation.pawn_position(PawnColor::Green),
+            Some(GridPosition { row: 0, column: 0 })
+        );
+        assert!(!simulation.step_back());
+    }
+
+    #[test]
+    fn only_steps_that_do_something_are_counted() {
+        let mut simulation = simulation("2 3 pZ1/3 0");
+
+        assert_eq!(
+            simulation.step(move_green(Direction::Left)),
+            StepOutcome::Blocked(BlockReason::Edge)
+        );
+        assert_eq!(
+            simulation.step(move_green(Direction::Right)),
+            StepOutcome::Blocked(BlockReason::Wall)
+        );
+        let orange = Instruction::CollectPerl {
+            pawn: PawnColor::Orange,
+        };
+        assert_eq!(simulation.step(orange), StepOutcome::NoSuchPawn);
+        assert_eq!(simulation.steps(), 0);
+
+        assert_eq!(
+            simulation.step(COLLECT_GREEN),
+            StepOutcome::NothingToCollect
+        );
+        let open_shell = Instruction::OpenShell {
+            pawn: PawnColor::Green,
+        };
+        assert_eq!(simulation.step(open_shell), StepOutcome::NoShell);
+        assert_eq!(
+            simulation.step(move_green(Direction::Down)),
+            StepOutcome::Moved
+        );
+        assert_eq!(simulation.steps(), 3);
+    }
+
+    #[test]
+    fn stone_is_pushed_onto_floor_but_not_into_a_wall() {
+        let mut simulation = simulation("2 4 pX1Z/4 0");
+        let stone = GridPosition { row: 0, column: 1 };
+
+        assert_eq!(
+            simulation.step(move_green(Direction::Right)),
+            StepOutcome::Pushed
+        );
+        assert_eq!(
+            simulation.stone_position(stone),
+            Some(GridPosition { row: 0, column: 2 })
+        );
+        assert_eq!(
+            simulation.pawn_position(PawnColor::Green),
+            Some(GridPosition { row: 0, column: 1 })
+        );
+
+        assert_eq!(
+            simulation.step(move_green(Direction::Right)),
+            StepOutcome::Blocked(BlockReason::Stone)
+        );
+        assert_eq!(
+            simulation.stone_position(stone),
+            Some(GridPosition { row: 0, column: 2 })
+        );
+        assert_eq!(simulation.steps(), 1);
+    }
+
+    #[test]
+    fn shells_toggle_every_shell_cycle() {
+        let mut simulation = simulation("1 3 po1 1");
+        let shell = GridPosition { row: 0, column: 1 };
+
+        assert_eq!(
+            simulation.step(move_green(Direction::Right)),
+            StepOutcome::Moved
+        );
+        for _ in 1..SHELL_CYCLE {
+            assert!(!simulation.is_shell_open(shell));
+            assert_eq!(simulation.step(COLLECT_GREEN), StepOutcome::ShellClosed);
+        }
+        assert!(simulation.is_shell_open(shell));
+        assert_eq!(simulation.collected_perls(), 0);
+    }
+
+    #[test]
+    fn pearl_is_collected_from_an_open_shell_once() {
+        let mut simulation = simulation("1 3 po1 1");
+        let shell = GridPosition { row: 0, column: 1 };
+        let open_shell = Instruction::OpenShell {
+            pawn: PawnColor::Green,
+        };
+
+        simulation.step(move_green(Direction::Right));
+        assert_eq!(simulation.step(open_shell), StepOutcome::ShellOpened);
+        assert_eq!(simulation.step(COLLECT_GREEN), StepOutcome::PerlCollected);
+        assert!(!simulation.shell_has_pearl(shell));
+        assert_eq!(simulation.collected_perls(), 1);
+
+        assert_eq!(
+            simulation.step(COLLECT_GREEN),
+            StepOutcome::NothingToCollect
+        );
+        assert_eq!(simulation.collected_perls(), 1);
+    }
+
+    #[test]
+    fn pawn_rule_decides_what_happens_when_pawns_meet() {
+        let green = GridPosition { row: 0, column: 0 };
+        let orange = GridPosition { row: 0, column: 1 };
+        let beyond = GridPosition { row: 0, column: 2 };
+
+        let mut block = simulation("1 3 pP1 0");
+        assert_eq!(
+            block.step(move_green(Direction::Right)),
+            StepOutcome::Blocked(BlockReason::Pawn(PawnColor::Orange))
+        );
+        assert_eq!(block.pawn_position(PawnColor::Green), Some(green));
+
+        let mut push = simulation("1 3 pP1 0 push");
+        assert_eq!(
+            push.step(move_green(Direction::Right)),
+            StepOutcome::PawnPushed
+        );
+        assert_eq!(push.pawn_position(PawnColor::Green), Some(orange));
+        assert_eq!(push.pawn_position(PawnColor::Orange), Some(beyond));
+        // The pushed pawn is at the edge now, so it does not move again
+        assert_eq!(
+            push.step(move_green(Direction::Right)),
+            StepOutcome::Blocked(BlockReason::Pawn(PawnColor::Orange))
+        );
+
+        let mut swap = simulation("1 3 pP1 0 swap");
+        assert_eq!(
+            swap.step(move_green(Direction::Right)),
+            StepOutcome::Swapped
+        );
+        assert_eq!(swap.pawn_position(PawnColor::Green), Some(orange));
+        assert_eq!(swap.pawn_position(PawnColor::Orange), Some(green));
+    }
+}
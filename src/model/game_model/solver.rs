@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+
+use super::{
+    game::Game,
+    simulation::{Direction, Instruction, Simulation, SimulationState, SHELL_CYCLE},
+};
+
+/// States the solver may visit before it gives up, keeps large open boards
+/// from stalling the game.
+pub const STATE_LIMIT: usize = 200_000;
+
+/// What the player should do next with the script they have built so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hint {
+    /// Append this block.
+    Next(Instruction),
+    /// Blocks from this index on cannot lead to a solution.
+    RemoveFrom(usize),
+    /// The script already collects every perl.
+    Done,
+    /// The level has no solution or the solver gave up.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    /// Shortest script that collects the required perls.
+    Solved(Vec<Instruction>),
+    /// Every reachable state was visited, at most `most_perls` can be collected.
+    Unsolvable {
+        most_perls: u32,
+    },
+    LimitReached,
+}
+
+impl Solution {
+    /// Number of blocks in the shortest script, the "par" of the level.
+    pub fn steps(&self) -> Option<u32> {
+        match self {
+            Solution::Solved(instructions) => Some(instructions.len() as u32),
+            _ => None,
+        }
+    }
+}
+
+pub fn solve(game: &Game, state_limit: usize) -> Solution {
+    solve_from(&Simulation::new(game), game.required_perls, state_limit)
+}
+
+/// Replays `script` and looks for the longest prefix that can still be finished,
+/// so a hint keeps as much of the player's own work as possible.
+pub fn hint(game: &Game, script: &[String], state_limit: usize) -> Hint {
+    let mut simulation = Simulation::new(game);
+    // states[k] is the state after the first k blocks
+    let mut states = vec![simulation.state().clone()];
+    for block in script {
+        match block.parse::<Instruction>() {
+            Ok(instruction) if simulation.step(instruction).counts_as_step() => {
+                states.push(simulation.state().clone())
+            }
+            _ => break,
+        }
+    }
+    for (prefix, state) in states.into_iter().enumerate().rev() {
+        simulation.set_state(state);
+        match solve_from(&simulation, game.required_perls, state_limit) {
+            Solution::Solved(_) if prefix < script.len() => return Hint::RemoveFrom(prefix),
+            Solution::Solved(solution) => {
+                return match solution.first() {
+                    Some(next) => Hint::Next(*next),
+                    None => Hint::Done,
+                }
+            }
+            Solution::Unsolvable { .. } => continue,
+            Solution::LimitReached => return Hint::Unknown,
+        }
+    }
+    Hint::Unknown
+}
+
+/// Breadth-first search over simulation states starting from the current state of `start`,
+/// so the first state that has `required_perls` is reached by a shortest script.
+/// Blocked instructions reset the level when a script runs, so they are never part of a solution.
+pub fn solve_from(start: &Simulation, required_perls: u32, state_limit: usize) -> Solution {
+    let instructions = candidate_instructions(start);
+    let mut simulation = start.clone();
+    // Every visited state with the index of its parent and the instruction that led to it
+    let mut nodes: Vec<(SimulationState, Option<(usize, Instruction)>)> =
+        vec![(start.state().clone(), None)];
+    let mut seen: HashSet<SimulationState> = HashSet::new();
+    seen.insert(state_key(start.state()));
+    let mut most_perls = 0;
+    let mut next = 0;
+    while next < nodes.len() {
+        most_perls = most_perls.max(nodes[next].0.collected_perls);
+        if nodes[next].0.collected_perls >= required_perls {
+            return Solution::Solved(script_to(&nodes, next));
+        }
+        for instruction in instructions.iter() {
+            simulation.set_state(nodes[next].0.clone());
+            if !simulation.step(*instruction).counts_as_step() {
+                continue;
+            }
+            let state = simulation.state().clone();
+            if seen.insert(state_key(&state)) {
+                if nodes.len() >= state_limit {
+                    return Solution::LimitReached;
+                }
+                nodes.push((state, Some((next, *instruction))));
+            }
+        }
+        next += 1;
+    }
+    Solution::Unsolvable { most_perls }
+}
+
+/// Every instruction that could matter on the level of `simulation`.
+pub fn candidate_instructions(simulation: &Simulation) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    for pawn in simulation.pawn_colors() {
+        for direction in [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ] {
+            instructions.push(Instruction::Move { pawn, direction });
+        }
+        instructions.push(Instruction::CollectPerl { pawn });
+        if !simulation.state().shells.is_empty() {
+            instructions.push(Instruction::OpenShell { pawn });
+        }
+    }
+    instructions
+}
+
+/// Two states behave the same from now on when they only differ in how many
+/// shell cycles have passed.
+fn state_key(state: &SimulationState) -> SimulationState {
+    let mut key = state.clone();
+    key.steps = if key.shells.is_empty() {
+        0
+    } else {
+        key.steps % SHELL_CYCLE
+    };
+    key
+}
+
+fn script_to(
+    nodes: &[(SimulationState, Option<(usize, Instruction)>)],
+    mut index: usize,
+) -> Vec<Instruction> {
+    let mut script = Vec::new();
+    while let Some((parent, instruction)) = nodes[index].1 {
+        script.push(instruction);
+        index = parent;
+    }
+    script.reverse();
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::game_model::simulation::PawnColor;
+
+    /// A stone below the only way to the perl. Pushing it up from the start
+    /// closes the way, walking around and pushing it aside does not.
+    const STONE_TRAP: &str = "4 3 ZCZ/Z1Z/1X1/1p1 1";
+
+    fn game(fen: &str) -> Game {
+        Game::try_from_fen(fen).unwrap()
+    }
+
+    fn move_green(direction: Direction) -> Instruction {
+        Instruction::Move {
+            pawn: PawnColor::Green,
+            direction,
+        }
+    }
+
+    fn script(blocks: &[&str]) -> Vec<String> {
+        blocks.iter().map(|block| block.to_string()).collect()
+    }
+
+    #[test]
+    fn solves_the_basics_pack_in_its_par() {
+        let pack: serde_json::Value =
+            serde_json::from_str(include_str!("../../../assets/levels/01_basics.json")).unwrap();
+        let mut pars = Vec::new();
+        for level in pack["levels"].as_array().unwrap() {
+            let solution = solve(&game(level["fen"].as_str().unwrap()), STATE_LIMIT);
+            assert_eq!(
+                solution.steps(),
+                level["par"].as_u64().map(|par| par as u32),
+                "{}",
+                level["id"]
+            );
+            pars.extend(solution.steps());
+        }
+        assert_eq!(pars, vec![3, 5, 8]);
+    }
+
+    #[test]
+    fn walled_off_perl_is_unsolvable() {
+        assert_eq!(
+            solve(&game("1 3 pZC 1"), STATE_LIMIT),
+            Solution::Unsolvable { most_perls: 0 }
+        );
+    }
+
+    #[test]
+    fn gives_up_at_the_state_limit() {
+        let solution = solve(&game("3 5 ZZZZZ/Zp1CZ/ZZZZZ 1"), 1);
+        assert_eq!(solution, Solution::LimitReached);
+        assert_eq!(solution.steps(), None);
+    }
+
+    #[test]
+    fn hint_continues_a_good_script() {
+        let game = game("3 5 ZZZZZ/Zp1CZ/ZZZZZ 1");
+        let right = move_green(Direction::Right);
+
+        assert_eq!(hint(&game, &[], STATE_LIMIT), Hint::Next(right));
+        assert_eq!(
+            hint(&game, &script(&["mgr"]), STATE_LIMIT),
+            Hint::Next(right)
+        );
+        assert_eq!(
+            hint(&game, &script(&["mgr", "mgr", "cgp"]), STATE_LIMIT),
+            Hint::Done
+        );
+    }
+
+    #[test]
+    fn hint_removes_blocks_that_lead_nowhere() {
+        let game = game(STONE_TRAP);
+        assert_eq!(solve(&game, STATE_LIMIT).steps(), Some(6));
+
+        // Pushing the stone up leaves it where it can only be pushed into the perl
+        assert_eq!(
+            hint(&game, &script(&["mgu", "mgl"]), STATE_LIMIT),
+            Hint::RemoveFrom(0)
+        );
+        // A blocked block resets the level, so the script is cut before it
+        assert_eq!(
+            hint(&game, &script(&["mgl", "mgd", "mgr"]), STATE_LIMIT),
+            Hint::RemoveFrom(1)
+        );
+        assert_eq!(
+            hint(&game, &script(&["mgl"]), STATE_LIMIT),
+            Hint::Next(move_green(Direction::Up))
+        );
+    }
+
+    #[test]
+    fn state_key_only_keeps_the_shell_cycle_of_the_steps() {
+        let plain = Simulation::new(&game("1 3 p1C 1"));
+        let mut later = plain.state().clone();
+        later.steps += 3;
+        assert_eq!(state_key(plain.state()), state_key(&later));
+
+        let shells = Simulation::new(&game("1 3 p1o 1"));
+        let mut next_cycle = shells.state().clone();
+        next_cycle.steps += SHELL_CYCLE;
+        let mut same_cycle = shells.state().clone();
+        same_cycle.steps += 1;
+        assert_eq!(state_key(shells.state()), state_key(&next_cycle));
+        assert_ne!(state_key(shells.state()), state_key(&same_cycle));
+    }
+}
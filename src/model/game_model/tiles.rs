@@ -0,0 +1,349 @@
+use std::f32::consts::PI;
+
+use self::TileKind::*;
+use super::{game::LevelCell, simulation::Direction};
+
+/// Family of a tile. Letters of the same family share sprite, shape and
+/// behavior and only differ by rotation and placement inside the cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileKind {
+    Empty,
+    BigBlock,
+    BigLeftTriangle,
+    BigRightTriangle,
+    BigLeftHalfSlope,
+    BigRightHalfSlope,
+    SmallBlock,
+    SmallLeftTriangle,
+    SmallRightTriangle,
+    SmallLeftHalfSlope,
+    SmallRightHalfSlope,
+    LowerHalfBlock,
+    CenterHalfBlock,
+    Pawn,
+    Stone,
+    Hexagon,
+    ClosedShell,
+    OpenShell,
+    Perl,
+}
+
+/// Part of the cell that a tile fills, before rotation.
+/// Triangles and slopes are named after the side their tall edge is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionShape {
+    None,
+    Full,
+    Quarter,
+    LeftTriangle,
+    RightTriangle,
+    LeftHalfSlope,
+    RightHalfSlope,
+    Half,
+    CenterBar,
+}
+
+/// What the simulation does with a tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileBehavior {
+    Empty,
+    Collider,
+    Movable,
+    Shell { open: bool },
+    Pearl,
+    Pawn,
+}
+
+impl TileKind {
+    pub fn sprite(&self) -> Option<&'static str> {
+        let path = match self {
+            // Pawns are drawn by colour, see `PawnColor::sprite`
+            TileKind::Empty | TileKind::Pawn => return None,
+            TileKind::BigBlock => "blocks/big_block.png",
+            TileKind::BigLeftTriangle => "blocks/big_left_triangle.png",
+            TileKind::BigRightTriangle => "blocks/big_right_triangle.png",
+            TileKind::BigLeftHalfSlope => "blocks/big_left_half_slope.png",
+            TileKind::BigRightHalfSlope => "blocks/big_right_half_slope.png",
+            TileKind::SmallBlock => "blocks/small_block.png",
+            TileKind::SmallLeftTriangle => "blocks/small_left_triangle.png",
+            TileKind::SmallRightTriangle => "blocks/small_right_triangle.png",
+            TileKind::SmallLeftHalfSlope => "blocks/small_left_half_slope.png",
+            TileKind::SmallRightHalfSlope => "blocks/small_right_half_slope.png",
+            TileKind::LowerHalfBlock => "blocks/lower_half_block.png",
+            TileKind::CenterHalfBlock => "blocks/center_half_block.png",
+            TileKind::Stone => "extra/stone.png",
+            TileKind::Hexagon => "extra/hexagon.png",
+            TileKind::ClosedShell => "extra/closed_shell.png",
+            TileKind::OpenShell => "extra/open_shell.png",
+            TileKind::Perl => "extra/perl.png",
+        };
+        Some(path)
+    }
+
+    pub fn shape(&self) -> CollisionShape {
+        match self {
+            TileKind::BigBlock | TileKind::Stone | TileKind::Hexagon => CollisionShape::Full,
+            TileKind::BigLeftTriangle | TileKind::SmallLeftTriangle => CollisionShape::LeftTriangle,
+            TileKind::BigRightTriangle | TileKind::SmallRightTriangle => {
+                CollisionShape::RightTriangle
+            }
+            TileKind::BigLeftHalfSlope | TileKind::SmallLeftHalfSlope => {
+                CollisionShape::LeftHalfSlope
+            }
+            TileKind::BigRightHalfSlope | TileKind::SmallRightHalfSlope => {
+                CollisionShape::RightHalfSlope
+            }
+            TileKind::SmallBlock => CollisionShape::Quarter,
+            TileKind::LowerHalfBlock => CollisionShape::Half,
+            TileKind::CenterHalfBlock => CollisionShape::CenterBar,
+            TileKind::Empty
+            | TileKind::Pawn
+            | TileKind::ClosedShell
+            | TileKind::OpenShell
+            | TileKind::Perl => CollisionShape::None,
+        }
+    }
+
+    pub fn behavior(&self) -> TileBehavior {
+        match self {
+            TileKind::Empty => TileBehavior::Empty,
+            TileKind::Pawn => TileBehavior::Pawn,
+            TileKind::Stone | TileKind::Hexagon => TileBehavior::Movable,
+            TileKind::ClosedShell => TileBehavior::Shell { open: false },
+            TileKind::OpenShell => TileBehavior::Shell { open: true },
+            TileKind::Perl => TileBehavior::Pearl,
+            _ => TileBehavior::Collider,
+        }
+    }
+}
+
+/// Size and offset of the sprite inside its cell, as fractions of the cell size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    pub width: f32,
+    pub height: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+}
+
+impl Footprint {
+    /// Whether the sprite touches the given edge of its cell. Sprites turn around
+    /// their centre, so odd quarter turns swap width and height.
+    pub fn reaches(&self, side: Direction, quarter_turns: u8) -> bool {
+        let center_x = self.offset_x + self.width / 2.0;
+        let center_y = self.offset_y + self.height / 2.0;
+        let (width, height) = if quarter_turns % 2 == 0 {
+            (self.width, self.height)
+        } else {
+            (self.height, self.width)
+        };
+        match side {
+            Direction::Up => center_y - height / 2.0 <= 0.0,
+            Direction::Down => center_y + height / 2.0 >= 1.0,
+            Direction::Left => center_x - width / 2.0 <= 0.0,
+            Direction::Right => center_x + width / 2.0 >= 1.0,
+        }
+    }
+}
+
+const fn footprint(width: f32, height: f32, offset_x: f32, offset_y: f32) -> Footprint {
+    Footprint {
+        width,
+        height,
+        offset_x,
+        offset_y,
+    }
+}
+
+const FULL: Footprint = footprint(1.0, 1.0, 0.0, 0.0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileSpec {
+    pub letter: char,
+    pub kind: TileKind,
+    pub quarter_turns: u8,
+    pub footprint: Footprint,
+}
+
+const fn tile(letter: char, kind: TileKind, quarter_turns: u8, footprint: Footprint) -> TileSpec {
+    TileSpec {
+        letter,
+        kind,
+        quarter_turns,
+        footprint,
+    }
+}
+
+/// Every letter a FEN board may contain.
+pub static TILES: &[TileSpec] = &[
+    tile('_', Empty, 0, FULL),
+    // Big blocks
+    tile('Z', BigBlock, 0, FULL),
+    tile('Q', BigLeftTriangle, 0, FULL),
+    tile('W', BigLeftTriangle, 1, FULL),
+    tile('E', BigLeftTriangle, 2, FULL),
+    tile('R', BigLeftTriangle, 3, FULL),
+    tile('T', BigRightTriangle, 0, FULL),
+    tile('Y', BigRightTriangle, 1, FULL),
+    tile('U', BigRightTriangle, 2, FULL),
+    tile('I', BigRightTriangle, 3, FULL),
+    tile('A', BigLeftHalfSlope, 0, FULL),
+    tile('S', BigLeftHalfSlope, 1, FULL),
+    tile('D', BigLeftHalfSlope, 2, FULL),
+    tile('F', BigLeftHalfSlope, 3, FULL),
+    tile('G', BigRightHalfSlope, 0, FULL),
+    tile('H', BigRightHalfSlope, 1, FULL),
+    tile('J', BigRightHalfSlope, 2, FULL),
+    tile('K', BigRightHalfSlope, 3, FULL),
+    // Small blocks
+    tile('z', SmallBlock, 0, footprint(0.5, 0.5, 0.0, 0.0)),
+    tile('x', SmallBlock, 0, footprint(0.5, 0.5, 0.5, 0.0)),
+    tile('c', SmallBlock, 0, footprint(0.5, 0.5, 0.5, 0.5)),
+    tile('v', SmallBlock, 0, footprint(0.5, 0.5, 0.0, 0.5)),
+    tile('q', SmallLeftTriangle, 0, footprint(0.5, 0.5, 0.0, 0.5)),
+    tile('w', SmallLeftTriangle, 1, footprint(0.5, 0.5, 0.0, 0.0)),
+    tile('e', SmallLeftTriangle, 2, footprint(0.5, 0.5, 0.5, 0.0)),
+    tile('r', SmallLeftTriangle, 3, footprint(0.5, 0.5, 0.5, 0.5)),
+    tile('t', SmallRightTriangle, 0, footprint(0.5, 0.5, 0.5, 0.5)),
+    tile('y', SmallRightTriangle, 1, footprint(0.5, 0.5, 0.0, 0.5)),
+    tile('u', SmallRightTriangle, 2, footprint(0.5, 0.5, 0.0, 0.0)),
+    tile('i', SmallRightTriangle, 0, footprint(0.5, 0.5, 0.0, 0.5)),
+    tile('a', SmallLeftHalfSlope, 0, footprint(1.0, 0.5, 0.0, 0.5)),
+    tile('s', SmallLeftHalfSlope, 1, footprint(1.0, 0.5, -0.25, 0.25)),
+    tile('d', SmallLeftHalfSlope, 2, footprint(1.0, 0.5, 0.0, 0.0)),
+    tile('f', SmallLeftHalfSlope, 3, footprint(1.0, 0.5, 0.25, 0.25)),
+    tile('g', SmallRightHalfSlope, 0, footprint(1.0, 0.5, 0.0, 0.5)),
+    tile(
+        'h',
+        SmallRightHalfSlope,
+        1,
+        footprint(1.0, 0.5, -0.25, 0.25),
+    ),
+    tile('j', SmallRightHalfSlope, 2, footprint(1.0, 0.5, 0.0, 0.5)),
+    tile('k', SmallRightHalfSlope, 3, footprint(1.0, 0.5, 0.25, 0.25)),
+    tile('b', LowerHalfBlock, 0, footprint(1.0, 0.5, 0.0, 0.5)),
+    tile('n', LowerHalfBlock, 1, footprint(1.0, 0.5, -0.25, 0.25)),
+    tile('m', LowerHalfBlock, 2, footprint(1.0, 0.5, 0.0, 0.0)),
+    tile('l', LowerHalfBlock, 3, footprint(1.0, 0.5, 0.25, 0.25)),
+    tile('B', CenterHalfBlock, 0, footprint(0.5, 1.0, 0.25, 0.0)),
+    tile('N', CenterHalfBlock, 1, footprint(0.5, 1.0, 0.25, 0.0)),
+    // Pawns 0 and 1, the others are written as "[n]"
+    tile('p', Pawn, 0, FULL),
+    tile('P', Pawn, 0, FULL),
+    // Extra
+    tile('X', Stone, 0, FULL),
+    tile('V', Hexagon, 0, FULL),
+    tile('o', ClosedShell, 0, footprint(1.0, 0.5, 0.0, 0.5)),
+    tile('O', OpenShell, 0, FULL),
+    tile('C', Perl, 0, footprint(0.5, 0.5, 0.25, 0.25)),
+];
+
+pub const EMPTY_LETTER: char = '_';
+pub const PERL_LETTER: char = 'C';
+
+pub fn tile_spec(letter: char) -> Option<&'static TileSpec> {
+    TILES.iter().find(|tile| tile.letter == letter)
+}
+
+impl TileSpec {
+    pub fn angle(&self) -> f32 {
+        PI / 2.0 * self.quarter_turns as f32
+    }
+
+    pub fn shape(&self) -> CollisionShape {
+        self.kind.shape()
+    }
+
+    pub fn behavior(&self) -> TileBehavior {
+        self.kind.behavior()
+    }
+
+    /// Whether the tile closes the given edge of its cell. Every quarter turn
+    /// rotates the sprite clockwise, so the edge is turned back before the lookup.
+    /// Tiles smaller than their cell only close the edges their sprite touches.
+    pub fn is_solid(&self, side: Direction) -> bool {
+        let mut unrotated = side;
+        for _ in 0..self.quarter_turns % 4 {
+            unrotated = unrotated.counter_clockwise();
+        }
+        let solid = match self.shape() {
+            CollisionShape::None => false,
+            // Quarters are placed by offset instead of rotation
+            CollisionShape::Full | CollisionShape::Quarter => true,
+            CollisionShape::LeftTriangle | CollisionShape::LeftHalfSlope => {
+                matches!(unrotated, Direction::Left | Direction::Down)
+            }
+            CollisionShape::RightTriangle | CollisionShape::RightHalfSlope => {
+                matches!(unrotated, Direction::Right | Direction::Down)
+            }
+            CollisionShape::Half => unrotated == Direction::Down,
+            // The bar splits the cell, so it closes the sides it faces without touching them
+            CollisionShape::CenterBar => {
+                return matches!(unrotated, Direction::Left | Direction::Right)
+            }
+        };
+        solid && self.footprint.reaches(side, self.quarter_turns)
+    }
+
+    pub fn level_cell(&self, image_size: f32) -> LevelCell {
+        LevelCell {
+            letter: self.letter,
+            angle: self.angle(),
+            image_size_x: image_size * self.footprint.width,
+            image_size_y: image_size * self.footprint.height,
+            extra_move_x: image_size * self.footprint.offset_x,
+            extra_move_y: image_size * self.footprint.offset_y,
+            pawn: None,
+            cell_entity: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIDES: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    #[test]
+    fn solid_edges_are_touched_by_the_sprite() {
+        for tile in TILES {
+            let touched: Vec<Direction> = SIDES
+                .into_iter()
+                .filter(|side| tile.footprint.reaches(*side, tile.quarter_turns))
+                .collect();
+            let solid: Vec<Direction> = SIDES
+                .into_iter()
+                .filter(|side| tile.is_solid(*side))
+                .collect();
+            match tile.shape() {
+                CollisionShape::None => assert!(solid.is_empty(), "'{}'", tile.letter),
+                CollisionShape::Full | CollisionShape::Quarter => {
+                    assert_eq!(solid, touched, "'{}'", tile.letter)
+                }
+                // The bar runs between the two sides it does not close
+                CollisionShape::CenterBar => {
+                    assert_eq!(solid.len(), 2, "'{}'", tile.letter);
+                    assert!(
+                        solid.iter().all(|side| !touched.contains(side)),
+                        "'{}'",
+                        tile.letter
+                    );
+                }
+                _ => {
+                    assert!(!solid.is_empty(), "'{}' closes no edge", tile.letter);
+                    assert!(
+                        solid.iter().all(|side| touched.contains(side)),
+                        "'{}' closes {solid:?}, but only touches {touched:?}",
+                        tile.letter
+                    );
+                }
+            }
+        }
+    }
+}
@@ -11,13 +11,21 @@ const LANGUAGE_FILE_FOLDER: &str = "assets/languages/";
 pub struct MainMenu {
     pub tutorial_button: String,
     pub challenge_button: String,
+    pub difficulty_buttons: Vec<String>,
+    pub challenge_code_label: String,
+    pub challenge_code_button: String,
+    pub challenge_code_error: String,
     pub multiplayer_button: String,
     pub scoreboard_button: String,
+    pub level_editor_button: String,
     pub language_panel: String,
     pub player_panel: String,
     pub create_new_player_button: String,
     pub exit_button: String,
     pub reload_text: String,
+    pub offline_banner: String,
+    pub offline_label: String,
+    pub no_player_label: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,10 +37,17 @@ pub struct LevelSelector {
     pub selected_button: String,
 }
 
+/// Button texts are templates: "{pawn}" and "{direction}" are replaced with
+/// entries of `pawn_names`/`pawn_actor_names` and `directions`.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PuzzleButtonPanel {
     pub label: String,
-    pub buttons: Vec<String>,
+    pub move_button: String,
+    pub collect_button: String,
+    pub open_shell_button: String,
+    pub directions: Vec<String>,
+    pub pawn_names: Vec<String>,
+    pub pawn_actor_names: Vec<String>,
     pub close_button: String,
 }
 
@@ -44,8 +59,11 @@ pub struct GameText {
     pub move_arrows_panel_label: String,
     pub menu_panel_label: String,
     pub complete_button: String,
+    pub hint_button: String,
     pub go_back_button: String,
     pub perls_score_label: String,
+    pub par_label: String,
+    pub level_error_label: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -54,6 +72,11 @@ pub struct ScoreboardText {
     pub challenge_scores_title: String,
     pub multiplayer_scores_title: String,
     pub go_back_button: String,
+    pub leaderboard_title: String,
+    pub your_rank_label: String,
+    pub personal_best_label: String,
+    pub not_played_label: String,
+    pub page_label: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -69,6 +92,36 @@ pub struct MultilayerText {
     pub go_back_button: String,
 }
 
+/// Status texts are templates: "{steps}" and "{perls}" are replaced with numbers.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LevelEditorText {
+    pub title: String,
+    pub rows_label: String,
+    pub columns_label: String,
+    pub goal_label: String,
+    pub name_label: String,
+    pub palette_label: String,
+    pub save_file_button: String,
+    pub save_db_button: String,
+    pub go_back_button: String,
+    pub invalid_label: String,
+    pub solvable_label: String,
+    pub unsolvable_label: String,
+    pub too_big_label: String,
+    pub checking_label: String,
+    pub saved_label: String,
+    pub not_saved_label: String,
+    pub level_mode_button: String,
+    pub prefab_mode_button: String,
+    pub pawns_label: String,
+    pub spots_label: String,
+    pub roll_button: String,
+    pub roll_label: String,
+    pub rolling_label: String,
+    pub no_solvable_rolls_label: String,
+    pub prefab_not_saved_label: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Resource)]
 pub struct LanguageResource {
     pub main_menu: MainMenu,
@@ -76,6 +129,13 @@ pub struct LanguageResource {
     pub game: GameText,
     pub scoreboard: ScoreboardText,
     pub multiplayer: MultilayerText,
+    pub level_editor: LevelEditorText,
+    /// Shown by the loading spinner while the database answers
+    pub loading_label: String,
+    /// Replaces the loading label when the database call failed
+    pub database_error_label: String,
+    /// Shown instead of a challenge when the database has no prefabs
+    pub no_challenges_label: String,
 }
 pub struct LanguagePlugin;
 
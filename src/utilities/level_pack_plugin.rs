@@ -0,0 +1,207 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const LEVEL_PACK_FOLDER: &str = "./assets/levels/";
+const PROGRESS_FILE_PATH: &str = "./assets/pack_progress.json";
+/// Pack the level editor saves its levels into.
+const EDITOR_PACK_FILE: &str = "editor_levels.json";
+const FALLBACK_LANGUAGE: &str = "English";
+
+/// One tutorial level stored in a level pack file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackLevel {
+    /// Unique across all packs, progress on the level is saved under it
+    pub id: String,
+    pub name: String,
+    /// Description per language, keyed by the language names of the config
+    #[serde(default)]
+    pub description: HashMap<String, String>,
+    pub fen: String,
+    #[serde(default)]
+    pub par: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl PackLevel {
+    /// Description in `language`, or in English when the pack has no translation.
+    pub fn description(&self, language: &str) -> &str {
+        self.description
+            .get(language)
+            .or_else(|| self.description.get(FALLBACK_LANGUAGE))
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+}
+
+/// A JSON file in `assets/levels/` holding a list of levels that are played in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelPack {
+    pub name: String,
+    pub levels: Vec<PackLevel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackSolution {
+    pub player_id: i32,
+    pub level_id: String,
+    pub number_of_steps: i32,
+}
+
+/// Hints a player took on one level, see `Game::hint_key`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HintCount {
+    pub player_id: i32,
+    pub level: String,
+    pub used: u32,
+}
+
+/// Best results on pack levels. Pack levels are not in the database, so their
+/// results are kept in a file next to the config. The hints taken on every
+/// level are kept with them, the database only records them for teachers.
+#[derive(Debug, Default, Resource, Serialize, Deserialize)]
+pub struct PackProgress {
+    pub solutions: Vec<PackSolution>,
+    #[serde(default)]
+    pub hints: Vec<HintCount>,
+}
+
+impl PackProgress {
+    fn load() -> PackProgress {
+        match fs::read_to_string(PROGRESS_FILE_PATH) {
+            Ok(progress) => serde_json::from_str(&progress).unwrap_or_else(|err| {
+                warn!("Pack progress file is broken, starting over: {err}");
+                PackProgress::default()
+            }),
+            Err(_) => PackProgress::default(),
+        }
+    }
+
+    pub fn best_steps(&self, player_id: i32, level_id: &str) -> Option<i32> {
+        self.solutions
+            .iter()
+            .find(|solution| solution.player_id == player_id && solution.level_id == level_id)
+            .map(|solution| solution.number_of_steps)
+    }
+
+    /// Keeps the result when it beats the previous one and writes the file.
+    pub fn update_score(&mut self, player_id: i32, level_id: &str, steps: i32) {
+        match self
+            .solutions
+            .iter_mut()
+            .find(|solution| solution.player_id == player_id && solution.level_id == level_id)
+        {
+            Some(solution) if solution.number_of_steps <= steps => return,
+            Some(solution) => solution.number_of_steps = steps,
+            None => self.solutions.push(PackSolution {
+                player_id,
+                level_id: level_id.to_string(),
+                number_of_steps: steps,
+            }),
+        }
+        self.save();
+    }
+
+    pub fn hints_used(&self, player_id: i32, level: &str) -> u32 {
+        self.hints
+            .iter()
+            .find(|count| count.player_id == player_id && count.level == level)
+            .map_or(0, |count| count.used)
+    }
+
+    pub fn add_hint(&mut self, player_id: i32, level: &str) {
+        match self
+            .hints
+            .iter_mut()
+            .find(|count| count.player_id == player_id && count.level == level)
+        {
+            Some(count) => count.used += 1,
+            None => self.hints.push(HintCount {
+                player_id,
+                level: level.to_string(),
+                used: 1,
+            }),
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let json_progress = serde_json::to_string(&self).expect("Progress should be serializable");
+        if let Err(err) = fs::write(PROGRESS_FILE_PATH, json_progress) {
+            warn!("Pack progress could not be saved: {err}");
+        }
+    }
+}
+
+pub struct LevelPackPlugin;
+
+impl Plugin for LevelPackPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PackProgress::load());
+    }
+}
+
+/// Reads every pack in `assets/levels/`, ordered by file name. Broken files are
+/// skipped with a warning so one bad pack does not hide the others.
+pub fn load_level_packs() -> Vec<LevelPack> {
+    let entries = match fs::read_dir(LEVEL_PACK_FOLDER) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+        .collect();
+    paths.sort();
+    let mut packs = Vec::new();
+    for path in paths {
+        match read_level_pack(&path) {
+            Ok(pack) => packs.push(pack),
+            Err(err) => warn!("Level pack {} is skipped: {err}", path.display()),
+        }
+    }
+    packs
+}
+
+fn read_level_pack(path: &Path) -> Result<LevelPack, Box<dyn std::error::Error>> {
+    let pack = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&pack)?)
+}
+
+/// Adds a level to the pack of the level editor, creating the pack on first use.
+pub fn save_to_editor_pack(
+    name: String,
+    fen: String,
+    par: Option<u32>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = Path::new(LEVEL_PACK_FOLDER).join(EDITOR_PACK_FILE);
+    let mut pack = if path.exists() {
+        read_level_pack(&path)?
+    } else {
+        LevelPack {
+            name: "Editor".to_string(),
+            levels: Vec::new(),
+        }
+    };
+    let mut number = pack.levels.len() + 1;
+    while pack
+        .levels
+        .iter()
+        .any(|level| level.id == format!("editor-{number}"))
+    {
+        number += 1;
+    }
+    pack.levels.push(PackLevel {
+        id: format!("editor-{number}"),
+        name,
+        description: HashMap::new(),
+        fen,
+        par,
+        tags: vec!["editor".to_string()],
+    });
+    fs::create_dir_all(LEVEL_PACK_FOLDER)?;
+    fs::write(path, serde_json::to_string_pretty(&pack)?)?;
+    Ok(())
+}
@@ -1,4 +1,7 @@
 pub mod database_plugin;
 pub mod language_plugin;
+pub mod level_pack_plugin;
 pub mod network_plugin;
+pub mod score_queue_plugin;
 pub mod script_plugin;
+pub mod storage;
@@ -112,6 +112,7 @@ pub struct NetworkResource {
     pub my_game_score: GameScore,
     pub opponent_game_score: GameScore,
     pub game_stage: GameStage,
+    pub level_error: Option<String>,
 }
 
 impl Default for NetworkResource {
@@ -125,6 +126,7 @@ impl Default for NetworkResource {
             my_game_score: GameScore::default(),
             opponent_game_score: GameScore::default(),
             game_stage: GameStage::Start,
+            level_error: None,
         }
     }
 }
@@ -263,13 +265,21 @@ fn receive_level_data_from_server(
                 network_res.level_selection_data.level_name = name;
             }
             ServerMessage::StartGame => {
-                *game = Game::init_from_fen(
+                match Game::init_from_fen(
                     network_res.level_selection_data.fen.clone(),
                     network_res.level_selection_data.level_id,
                     GameMode::Multiplayer,
-                );
-                *script_res = ScriptRes::new();
-                game_state.set(GameState::Game).unwrap();
+                ) {
+                    Ok(new_game) => {
+                        network_res.level_error = None;
+                        *game = new_game;
+                        *script_res = ScriptRes::new();
+                        game_state.set(GameState::Game).unwrap();
+                    }
+                    Err(err) => {
+                        network_res.level_error = Some(err.to_string());
+                    }
+                }
             }
             ServerMessage::ScoreResult { num_of_steps } => {
                 network_res.opponent_game_score.complete(num_of_steps);
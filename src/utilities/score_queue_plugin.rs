@@ -0,0 +1,366 @@
+use std::fs;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    database_plugin::{
+        add_db_task, random_player_name, update_cofig_file, ConfigResource, DbError, DbResult,
+        DbTask, Player,
+    },
+    storage::{ChallengeResult, GameStore, HintUsage, ScoreStore, Storage, StoreResult},
+};
+
+const QUEUE_FILE_PATH: &str = "./assets/score_queue.json";
+/// How often scores that did not reach the database are sent again
+const SYNC_SECONDS: f32 = 10.0;
+
+/// A result of a finished level, or a hint taken on it, that has to reach the database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ScoreWrite {
+    Tutorial {
+        player_id: i32,
+        level_id: i32,
+        steps: i32,
+    },
+    Challenge(ChallengeResult),
+    Multiplayer {
+        player_id: i32,
+        fen: String,
+        num_of_steps: i32,
+        prefab_id: i32,
+    },
+    Hint(HintUsage),
+}
+
+impl ScoreWrite {
+    fn player_id(&self) -> i32 {
+        match self {
+            ScoreWrite::Tutorial { player_id, .. } => *player_id,
+            ScoreWrite::Challenge(result) => result.player_id,
+            ScoreWrite::Multiplayer { player_id, .. } => *player_id,
+            ScoreWrite::Hint(usage) => usage.player_id,
+        }
+    }
+
+    fn level_id(&self) -> i32 {
+        match self {
+            ScoreWrite::Tutorial { level_id, .. } => *level_id,
+            ScoreWrite::Challenge(result) => result.prefab_id,
+            ScoreWrite::Multiplayer { prefab_id, .. } => *prefab_id,
+            ScoreWrite::Hint(usage) => usage.level_id.unwrap_or_default(),
+        }
+    }
+
+    fn set_player_id(&mut self, id: i32) {
+        match self {
+            ScoreWrite::Tutorial { player_id, .. } => *player_id = id,
+            ScoreWrite::Challenge(result) => result.player_id = id,
+            ScoreWrite::Multiplayer { player_id, .. } => *player_id = id,
+            ScoreWrite::Hint(usage) => usage.player_id = id,
+        }
+    }
+
+    fn apply(&self, attempt_id: u64, store: &mut dyn GameStore) -> StoreResult<()> {
+        match self {
+            // Only the best tutorial result is kept, so writing one twice does no harm
+            ScoreWrite::Tutorial {
+                player_id,
+                level_id,
+                steps,
+            } => store.update_tutorial_score(*player_id, *level_id, *steps),
+            ScoreWrite::Challenge(result) => store.save_challenge_result(attempt_id, result),
+            ScoreWrite::Multiplayer {
+                player_id,
+                fen,
+                num_of_steps,
+                prefab_id,
+            } => store.save_multiplayer_result(
+                attempt_id,
+                *player_id,
+                fen,
+                *num_of_steps,
+                *prefab_id,
+            ),
+            ScoreWrite::Hint(usage) => store.save_hint_usage(usage),
+        }
+    }
+}
+
+/// One attempt at a level. The attempt id is picked when the level is finished,
+/// so an attempt that fails again on replay is still queued only once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedScore {
+    pub attempt_id: u64,
+    pub write: ScoreWrite,
+}
+
+impl QueuedScore {
+    fn key(&self) -> (i32, i32, u64) {
+        (
+            self.write.player_id(),
+            self.write.level_id(),
+            self.attempt_id,
+        )
+    }
+}
+
+/// Scores that could not be written to the database yet. They are kept in a
+/// file next to the config, so closing the game does not lose them.
+#[derive(Debug, Default, Resource, Serialize, Deserialize)]
+pub struct ScoreQueue {
+    pub scores: Vec<QueuedScore>,
+}
+
+impl ScoreQueue {
+    fn load() -> ScoreQueue {
+        match fs::read_to_string(QUEUE_FILE_PATH) {
+            Ok(queue) => serde_json::from_str(&queue).unwrap_or_else(|err| {
+                warn!("Score queue file is broken, starting over: {err}");
+                ScoreQueue::default()
+            }),
+            Err(_) => ScoreQueue::default(),
+        }
+    }
+
+    fn push(&mut self, score: QueuedScore) {
+        if self.scores.iter().any(|queued| queued.key() == score.key()) {
+            return;
+        }
+        self.scores.push(score);
+        self.save();
+    }
+
+    fn remove(&mut self, synced: &[QueuedScore]) {
+        let len = self.scores.len();
+        self.scores
+            .retain(|queued| !synced.iter().any(|score| score.key() == queued.key()));
+        if self.scores.len() != len {
+            self.save();
+        }
+    }
+
+    /// Hands the scores of a player made offline to the id the database gave it.
+    fn replace_player(&mut self, local_id: i32, id: i32) {
+        let mut replaced = false;
+        for queued in &mut self.scores {
+            if queued.write.player_id() == local_id {
+                queued.write.set_player_id(id);
+                replaced = true;
+            }
+        }
+        if replaced {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let json_queue = serde_json::to_string(&self).expect("Score queue should be serializable");
+        if let Err(err) = fs::write(QUEUE_FILE_PATH, json_queue) {
+            warn!("Score queue could not be saved: {err}");
+        }
+    }
+}
+
+/// Scores a `DbTask` tried to write, split by whether the store took them.
+pub struct SyncedScores {
+    pub synced: Vec<QueuedScore>,
+    pub failed: Vec<QueuedScore>,
+    /// The first error, a lost connection takes the game offline
+    pub error: Option<DbError>,
+}
+
+/// Players made offline with the player the database created for each of them,
+/// by their local id. When a player could not be added the ones before it are
+/// still kept, so they are not added a second time.
+pub struct RegisteredPlayers {
+    pub players: Vec<(i32, Player)>,
+    pub error: Option<DbError>,
+}
+
+#[derive(Resource)]
+struct SyncTimer(Timer);
+
+pub struct ScoreQueuePlugin;
+
+impl Plugin for ScoreQueuePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ScoreQueue::load())
+            .insert_resource(SyncTimer(Timer::from_seconds(
+                SYNC_SECONDS,
+                TimerMode::Repeating,
+            )))
+            .add_system(sync_score_queue)
+            .add_system(update_score_queue)
+            .add_system(register_offline_players)
+            // The ids of the last registration have to be in the config before
+            // the next one looks for offline players
+            .add_system(update_registered_players.before(register_offline_players));
+        add_db_task::<SyncedScores>(app);
+        add_db_task::<RegisteredPlayers>(app);
+    }
+}
+
+/// Writes the score of a finished level. It goes to the queue when there is no
+/// database or the write fails, and is sent again once the database is back.
+pub fn save_score(
+    commands: &mut Commands,
+    storage: Option<&Storage>,
+    queue: &mut ScoreQueue,
+    write: ScoreWrite,
+) {
+    let score = QueuedScore {
+        attempt_id: rand::thread_rng().gen(),
+        write,
+    };
+    match storage {
+        Some(storage) if score.write.player_id() >= 0 => {
+            commands.spawn(write_scores(storage, vec![score]));
+        }
+        _ => queue.push(score),
+    }
+}
+
+fn write_scores(storage: &Storage, scores: Vec<QueuedScore>) -> DbTask<SyncedScores> {
+    DbTask::new(storage, move |store| {
+        let (mut synced, mut failed) = (Vec::new(), Vec::new());
+        let mut error = None;
+        for score in scores {
+            // Once a write failed the rest wait in the queue for the next sync
+            if error.is_some() {
+                failed.push(score);
+                continue;
+            }
+            match score.write.apply(score.attempt_id, store) {
+                Ok(()) => synced.push(score),
+                Err(err) => {
+                    warn!("Score is kept until the database is back: {err}");
+                    error = Some(DbError::new(&err));
+                    failed.push(score);
+                }
+            }
+        }
+        Ok(SyncedScores {
+            synced,
+            failed,
+            error,
+        })
+    })
+}
+
+fn sync_score_queue(
+    mut commands: Commands,
+    mut timer: ResMut<SyncTimer>,
+    pending: Query<(), With<DbTask<SyncedScores>>>,
+    queue: Res<ScoreQueue>,
+    storage: Option<Res<Storage>>,
+    time: Res<Time>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() || !pending.is_empty() {
+        return;
+    }
+    let storage = match storage {
+        Some(storage) => storage,
+        None => return,
+    };
+    // Players made offline only have a local id the database does not know,
+    // their scores wait until `register_offline_players` gave them a real one
+    let scores: Vec<QueuedScore> = queue
+        .scores
+        .iter()
+        .filter(|score| score.write.player_id() >= 0)
+        .cloned()
+        .collect();
+    if !scores.is_empty() {
+        commands.spawn(write_scores(&storage, scores));
+    }
+}
+
+fn update_score_queue(
+    mut results: EventReader<DbResult<SyncedScores>>,
+    mut queue: ResMut<ScoreQueue>,
+    mut errors: EventWriter<DbError>,
+) {
+    for DbResult(scores) in results.iter() {
+        queue.remove(&scores.synced);
+        for score in &scores.failed {
+            queue.push(score.clone());
+        }
+        if let Some(err) = &scores.error {
+            errors.send(err.clone());
+        }
+    }
+}
+
+/// Creates the players made offline on the database once it is back, their
+/// queued scores are synced after they have a real id.
+fn register_offline_players(
+    mut commands: Commands,
+    pending: Query<(), With<DbTask<RegisteredPlayers>>>,
+    config: Res<ConfigResource>,
+    storage: Option<Res<Storage>>,
+) {
+    let storage = match storage {
+        Some(storage) if pending.is_empty() => storage,
+        _ => return,
+    };
+    let offline_players: Vec<Player> = config
+        .local_players
+        .iter()
+        .filter(|player| player.id < 0)
+        .cloned()
+        .collect();
+    if offline_players.is_empty() {
+        return;
+    }
+    commands.spawn(DbTask::new(&storage, move |store| {
+        let mut taken_names = store.player_names()?;
+        let mut players = Vec::new();
+        for player in offline_players {
+            // Somebody else may have taken the name while the game was offline
+            let name = if taken_names.contains(&player.name) {
+                random_player_name(&taken_names)
+            } else {
+                player.name
+            };
+            let id = match store.add_player(&name) {
+                Ok(id) => id,
+                Err(err) => {
+                    return Ok(RegisteredPlayers {
+                        players,
+                        error: Some(DbError::new(&err)),
+                    })
+                }
+            };
+            taken_names.push(name.clone());
+            players.push((player.id, Player { id, name }));
+        }
+        Ok(RegisteredPlayers {
+            players,
+            error: None,
+        })
+    }));
+}
+
+fn update_registered_players(
+    mut results: EventReader<DbResult<RegisteredPlayers>>,
+    mut config: ResMut<ConfigResource>,
+    mut queue: ResMut<ScoreQueue>,
+    mut errors: EventWriter<DbError>,
+) {
+    for DbResult(RegisteredPlayers { players, error }) in results.iter() {
+        for (local_id, player) in players {
+            for local_player in &mut config.local_players {
+                if local_player.id == *local_id {
+                    *local_player = player.clone();
+                }
+            }
+            queue.replace_player(*local_id, player.id);
+        }
+        update_cofig_file(&mut config);
+        if let Some(err) = error {
+            errors.send(err.clone());
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use super::{
+    AllLevelsWithSolutions, ChallengeResult, ChallengeScore, FenPrefab, HintUsage,
+    LeaderboardEntry, LevelStore, PlayerStanding, ScoreStore, StoreResult,
+};
+
+struct TutorialSolution {
+    player_id: i32,
+    level_id: i32,
+    number_of_steps: i32,
+}
+
+struct PrefabSolution {
+    attempt_id: u64,
+    player_id: i32,
+    prefab_id: i32,
+    num_of_steps: i32,
+}
+
+/// Keeps everything in memory, for trying the game out without any database.
+#[derive(Default)]
+pub struct MemoryStore {
+    players: Vec<String>,
+    tutorial_levels: Vec<(String, String)>,
+    tutorial_solutions: Vec<TutorialSolution>,
+    challenge_prefabs: Vec<FenPrefab>,
+    challenge_solutions: Vec<PrefabSolution>,
+    multiplayer_solutions: Vec<PrefabSolution>,
+}
+
+impl MemoryStore {
+    fn scores(
+        &self,
+        solutions: &[PrefabSolution],
+        player_id: i32,
+        limit: u32,
+    ) -> Vec<ChallengeScore> {
+        let mut scores: Vec<ChallengeScore> = solutions
+            .iter()
+            .filter(|solution| solution.player_id == player_id)
+            .map(|solution| ChallengeScore {
+                prefab_id: solution.prefab_id,
+                level_name: self
+                    .challenge_prefabs
+                    .iter()
+                    .find(|prefab| prefab.prefab_id == solution.prefab_id)
+                    .map(|prefab| prefab.level_name.clone())
+                    .unwrap_or_default(),
+                num_of_steps: solution.num_of_steps,
+            })
+            .collect();
+        // Stable, so equal results stay in the order they were saved
+        scores.sort_by_key(|score| score.num_of_steps);
+        scores.truncate(limit as usize);
+        scores
+    }
+
+    /// Best challenge result of every player on the prefab with their rank, best first.
+    fn ranked_players(&self, prefab_id: i32) -> Vec<LeaderboardEntry> {
+        let mut best: Vec<(i32, i32)> = Vec::new();
+        for solution in &self.challenge_solutions {
+            if solution.prefab_id != prefab_id {
+                continue;
+            }
+            match best
+                .iter_mut()
+                .find(|(player_id, _)| *player_id == solution.player_id)
+            {
+                Some((_, steps)) => *steps = (*steps).min(solution.num_of_steps),
+                None => best.push((solution.player_id, solution.num_of_steps)),
+            }
+        }
+        best.sort_by_key(|&(player_id, steps)| (steps, player_id));
+        best.iter()
+            .map(|&(player_id, num_of_steps)| LeaderboardEntry {
+                rank: best
+                    .iter()
+                    .filter(|(_, steps)| *steps < num_of_steps)
+                    .count() as u32
+                    + 1,
+                player_id,
+                player_name: usize::try_from(player_id - 1)
+                    .ok()
+                    .and_then(|ind| self.players.get(ind))
+                    .cloned()
+                    .unwrap_or_default(),
+                num_of_steps,
+            })
+            .collect()
+    }
+}
+
+fn save_once(solutions: &mut Vec<PrefabSolution>, solution: PrefabSolution) {
+    if !solutions
+        .iter()
+        .any(|saved| saved.attempt_id == solution.attempt_id)
+    {
+        solutions.push(solution);
+    }
+}
+
+// Ids start at 1 like the auto increment columns of the databases
+impl LevelStore for MemoryStore {
+    fn levels_for_player(&mut self, player_id: i32) -> StoreResult<Vec<AllLevelsWithSolutions>> {
+        let (mut solved, mut unsolved): (Vec<_>, Vec<_>) = self
+            .tutorial_levels
+            .iter()
+            .enumerate()
+            .map(|(ind, (description, fen))| {
+                let level_id = ind as i32 + 1;
+                AllLevelsWithSolutions {
+                    level_id,
+                    level_description: description.clone(),
+                    fen: fen.clone(),
+                    number_of_steps: self
+                        .tutorial_solutions
+                        .iter()
+                        .find(|solution| {
+                            solution.player_id == player_id && solution.level_id == level_id
+                        })
+                        .map(|solution| solution.number_of_steps),
+                }
+            })
+            .partition(|level| level.number_of_steps.is_some());
+        solved.append(&mut unsolved);
+        Ok(solved)
+    }
+
+    fn save_tutorial_level(&mut self, description: &str, fen: &str) -> StoreResult<()> {
+        self.tutorial_levels
+            .push((description.to_string(), fen.to_string()));
+        Ok(())
+    }
+
+    fn challenge_prefabs(&mut self) -> StoreResult<Vec<FenPrefab>> {
+        Ok(self
+            .challenge_prefabs
+            .iter()
+            .map(|prefab| FenPrefab {
+                prefab_id: prefab.prefab_id,
+                fen: prefab.fen.clone(),
+                level_name: prefab.level_name.clone(),
+            })
+            .collect())
+    }
+
+    fn challenge_prefab(&mut self, prefab_id: i32) -> StoreResult<Option<String>> {
+        Ok(self
+            .challenge_prefabs
+            .iter()
+            .find(|prefab| prefab.prefab_id == prefab_id)
+            .map(|prefab| prefab.fen.clone()))
+    }
+
+    fn save_challenge_prefab(&mut self, level_name: &str, prefab: &str) -> StoreResult<()> {
+        self.challenge_prefabs.push(FenPrefab {
+            prefab_id: self.challenge_prefabs.len() as i32 + 1,
+            fen: prefab.to_string(),
+            level_name: level_name.to_string(),
+        });
+        Ok(())
+    }
+}
+
+impl ScoreStore for MemoryStore {
+    fn player_names(&mut self) -> StoreResult<Vec<String>> {
+        Ok(self.players.clone())
+    }
+
+    // The databases have a unique key on the player name
+    fn add_player(&mut self, name: &str) -> StoreResult<i32> {
+        if self.players.iter().any(|player| player == name) {
+            return Err(format!("player name {name} is taken").into());
+        }
+        self.players.push(name.to_string());
+        Ok(self.players.len() as i32)
+    }
+
+    fn update_tutorial_score(
+        &mut self,
+        player_id: i32,
+        level_id: i32,
+        steps: i32,
+    ) -> StoreResult<()> {
+        match self
+            .tutorial_solutions
+            .iter_mut()
+            .find(|solution| solution.player_id == player_id && solution.level_id == level_id)
+        {
+            Some(solution) if solution.number_of_steps > steps => solution.number_of_steps = steps,
+            Some(_) => {}
+            None => self.tutorial_solutions.push(TutorialSolution {
+                player_id,
+                level_id,
+                number_of_steps: steps,
+            }),
+        }
+        Ok(())
+    }
+
+    fn save_challenge_result(
+        &mut self,
+        attempt_id: u64,
+        result: &ChallengeResult,
+    ) -> StoreResult<()> {
+        save_once(
+            &mut self.challenge_solutions,
+            PrefabSolution {
+                attempt_id,
+                player_id: result.player_id,
+                prefab_id: result.prefab_id,
+                num_of_steps: result.num_of_steps,
+            },
+        );
+        Ok(())
+    }
+
+    fn save_multiplayer_result(
+        &mut self,
+        attempt_id: u64,
+        player_id: i32,
+        _fen: &str,
+        num_of_steps: i32,
+        prefab_id: i32,
+    ) -> StoreResult<()> {
+        save_once(
+            &mut self.multiplayer_solutions,
+            PrefabSolution {
+                attempt_id,
+                player_id,
+                prefab_id,
+                num_of_steps,
+            },
+        );
+        Ok(())
+    }
+
+    // Hint usage is only read by teachers from a real database
+    fn save_hint_usage(&mut self, _usage: &HintUsage) -> StoreResult<()> {
+        Ok(())
+    }
+
+    fn best_challenge_scores(
+        &mut self,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>> {
+        Ok(self.scores(&self.challenge_solutions, player_id, limit))
+    }
+
+    fn best_multiplayer_scores(
+        &mut self,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>> {
+        Ok(self.scores(&self.multiplayer_solutions, player_id, limit))
+    }
+
+    fn leaderboard(
+        &mut self,
+        prefab_id: i32,
+        offset: u32,
+        limit: u32,
+    ) -> StoreResult<Vec<LeaderboardEntry>> {
+        Ok(self
+            .ranked_players(prefab_id)
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    fn leaderboard_size(&mut self, prefab_id: i32) -> StoreResult<u32> {
+        Ok(self.ranked_players(prefab_id).len() as u32)
+    }
+
+    fn player_standing(
+        &mut self,
+        prefab_id: i32,
+        player_id: i32,
+    ) -> StoreResult<Option<PlayerStanding>> {
+        Ok(self
+            .ranked_players(prefab_id)
+            .into_iter()
+            .find(|entry| entry.player_id == player_id)
+            .map(|entry| PlayerStanding {
+                rank: entry.rank,
+                personal_best: entry.num_of_steps,
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::storage::tests::{leaderboard_ranks_and_pages, names_round_trip};
+
+    #[test]
+    fn names_with_quotes_and_unicode_round_trip() {
+        names_round_trip(&mut MemoryStore::default(), "1");
+    }
+
+    #[test]
+    fn leaderboard_shares_ranks_and_pages() {
+        leaderboard_ranks_and_pages(&mut MemoryStore::default(), "1");
+    }
+
+    #[test]
+    fn taken_player_name_is_rejected() {
+        let mut store = MemoryStore::default();
+        assert_eq!(store.add_player("Anna").unwrap(), 1);
+        assert!(store.add_player("Anna").is_err());
+        assert_eq!(store.add_player("Boris").unwrap(), 2);
+    }
+}
@@ -0,0 +1,54 @@
+/// One step of the database schema, written once per SQL dialect.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub mysql: &'static str,
+    pub sqlite: &'static str,
+}
+
+/// Every schema change in order. Versions start at 1 and have no gaps, a
+/// database at version `n` has the first `n` migrations applied. Applied
+/// migrations are never edited, a change to the schema is a new migration.
+pub const MIGRATIONS: [Migration; 4] = [
+    Migration {
+        version: 1,
+        description: "initial schema",
+        mysql: include_str!("../../../migrations/mysql/001_initial_schema.sql"),
+        sqlite: include_str!("../../../migrations/sqlite/001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "challenge seeds and hint usage",
+        mysql: include_str!("../../../migrations/mysql/002_challenge_seeds_and_hints.sql"),
+        sqlite: include_str!("../../../migrations/sqlite/002_challenge_seeds_and_hints.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "score attempt ids",
+        mysql: include_str!("../../../migrations/mysql/003_score_attempt_ids.sql"),
+        sqlite: include_str!("../../../migrations/sqlite/003_score_attempt_ids.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "pack level hints",
+        mysql: include_str!("../../../migrations/mysql/004_pack_level_hints.sql"),
+        sqlite: include_str!("../../../migrations/sqlite/004_pack_level_hints.sql"),
+    },
+];
+
+/// Keeps the version of every applied migration, created before the first one runs.
+pub const SCHEMA_VERSION_TABLE: &str = r"CREATE TABLE IF NOT EXISTS schema_version (
+    version INTEGER NOT NULL PRIMARY KEY,
+    description VARCHAR(255) NOT NULL
+);";
+
+/// Migrations a database at `version` still needs.
+pub fn pending_migrations(version: u32) -> Result<&'static [Migration], String> {
+    let latest = MIGRATIONS.len() as u32;
+    if version > latest {
+        return Err(format!(
+            "database schema is at version {version} but this game only knows {latest}, update the game"
+        ));
+    }
+    Ok(&MIGRATIONS[version as usize..])
+}
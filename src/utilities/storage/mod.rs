@@ -0,0 +1,364 @@
+use std::{
+    error::Error,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::model::game_model::generator::BoardSeed;
+
+use self::mysql_store::MysqlConfig;
+
+pub mod memory_store;
+pub mod migrations;
+pub mod mysql_store;
+pub mod sqlite_store;
+
+#[derive(Clone, Debug)]
+pub struct ChallengeScore {
+    pub prefab_id: i32,
+    pub level_name: String,
+    pub num_of_steps: i32,
+}
+
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct FenPrefab {
+    pub prefab_id: i32,
+    pub fen: String,
+    pub level_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AllLevelsWithSolutions {
+    pub level_id: i32,
+    pub level_description: String,
+    pub fen: String,
+    pub number_of_steps: Option<i32>,
+}
+
+/// The best challenge result of one player on a prefab.
+#[derive(Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub player_id: i32,
+    pub player_name: String,
+    pub num_of_steps: i32,
+}
+
+/// Where one player is on the leaderboard of a prefab.
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerStanding {
+    pub rank: u32,
+    pub personal_best: i32,
+}
+
+/// A finished challenge board.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengeResult {
+    pub player_id: i32,
+    pub fen: String,
+    pub num_of_steps: i32,
+    pub prefab_id: i32,
+    pub board_seed: Option<BoardSeed>,
+    pub optimal_steps: Option<u32>,
+}
+
+/// One hint taken by a player, kept so teachers can see where students got stuck.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HintUsage {
+    pub player_id: i32,
+    /// `None` for level pack levels, they are not in the database
+    pub level_id: Option<i32>,
+    pub pack_level: Option<String>,
+    pub game_mode: String,
+    pub fen: String,
+    pub script_length: usize,
+}
+
+/// Whatever a store call failed with, the stores use their own error types.
+pub type StoreError = Box<dyn Error + Send + Sync>;
+
+/// Writes that return an error are tried again later. When the error says the
+/// database can not be reached, see `connection_lost`, the game goes offline
+/// until the store can be opened again.
+pub type StoreResult<T> = Result<T, StoreError>;
+
+/// Whether the error means the store can not be reached anymore, rather than that
+/// one call was wrong. Only the MySQL store has a connection that can drop.
+pub fn connection_lost(err: &StoreError) -> bool {
+    mysql_store::is_connection_error(err.as_ref())
+}
+
+/// Where tutorial levels and challenge prefabs are kept.
+pub trait LevelStore {
+    /// Tutorial levels solved by the player with their best result, then the unsolved ones.
+    fn levels_for_player(&mut self, player_id: i32) -> StoreResult<Vec<AllLevelsWithSolutions>>;
+    fn save_tutorial_level(&mut self, description: &str, fen: &str) -> StoreResult<()>;
+    fn challenge_prefabs(&mut self) -> StoreResult<Vec<FenPrefab>>;
+    fn challenge_prefab(&mut self, prefab_id: i32) -> StoreResult<Option<String>>;
+    fn save_challenge_prefab(&mut self, level_name: &str, prefab: &str) -> StoreResult<()>;
+}
+
+/// Where players and their results are kept.
+pub trait ScoreStore {
+    fn player_names(&mut self) -> StoreResult<Vec<String>>;
+    /// Adds a player and returns the id it got.
+    fn add_player(&mut self, name: &str) -> StoreResult<i32>;
+    /// Keeps the result when the player has not solved the level yet or did worse before.
+    fn update_tutorial_score(
+        &mut self,
+        player_id: i32,
+        level_id: i32,
+        steps: i32,
+    ) -> StoreResult<()>;
+    /// Results are saved once per `attempt_id`, writing the same attempt again
+    /// does nothing.
+    fn save_challenge_result(
+        &mut self,
+        attempt_id: u64,
+        result: &ChallengeResult,
+    ) -> StoreResult<()>;
+    fn save_multiplayer_result(
+        &mut self,
+        attempt_id: u64,
+        player_id: i32,
+        fen: &str,
+        num_of_steps: i32,
+        prefab_id: i32,
+    ) -> StoreResult<()>;
+    fn save_hint_usage(&mut self, usage: &HintUsage) -> StoreResult<()>;
+    /// The `limit` best challenge results of the player, fewest steps first.
+    fn best_challenge_scores(
+        &mut self,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>>;
+    /// The `limit` best multiplayer results of the player, fewest steps first.
+    fn best_multiplayer_scores(
+        &mut self,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>>;
+    /// The best challenge result of every player on the prefab, fewest steps first.
+    /// Players with the same steps share a rank and the ranks after them are
+    /// skipped, e.g. 1, 2, 2, 4.
+    fn leaderboard(
+        &mut self,
+        prefab_id: i32,
+        offset: u32,
+        limit: u32,
+    ) -> StoreResult<Vec<LeaderboardEntry>>;
+    /// Number of players on the leaderboard of the prefab.
+    fn leaderboard_size(&mut self, prefab_id: i32) -> StoreResult<u32>;
+    /// `None` when the player has not solved the prefab yet.
+    fn player_standing(
+        &mut self,
+        prefab_id: i32,
+        player_id: i32,
+    ) -> StoreResult<Option<PlayerStanding>>;
+}
+
+pub trait GameStore: LevelStore + ScoreStore + Send {}
+
+impl<T: LevelStore + ScoreStore + Send> GameStore for T {}
+
+/// The backend the game keeps its levels and scores in, chosen by `StorageConfig`.
+/// Every call runs on the `AsyncComputeTaskPool`, so a slow network never freezes
+/// the window.
+#[derive(Resource, Clone)]
+pub struct Storage(Arc<Mutex<Box<dyn GameStore>>>);
+
+impl Storage {
+    pub fn new(store: impl GameStore + 'static) -> Storage {
+        Storage(Arc::new(Mutex::new(Box::new(store))))
+    }
+
+    /// Runs `call` on the store in the background.
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&mut dyn GameStore) -> StoreResult<T> + Send + 'static,
+    ) -> Task<StoreResult<T>> {
+        self.spawn_then(call, |result| result)
+    }
+
+    /// Runs `call` on the store in the background and `then` on what it read once
+    /// the store is unlocked again, so slow work like generating boards does not
+    /// hold up the other calls.
+    pub fn spawn_then<R: Send + 'static, T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&mut dyn GameStore) -> StoreResult<R> + Send + 'static,
+        then: impl FnOnce(R) -> T + Send + 'static,
+    ) -> Task<StoreResult<T>> {
+        let store = self.0.clone();
+        AsyncComputeTaskPool::get().spawn(async move {
+            let read = {
+                // Stores return their errors instead of panicking, a poisoned lock
+                // still holds a store that can be used
+                let mut store = store.lock().unwrap_or_else(PoisonError::into_inner);
+                call(store.as_mut())
+            };
+            read.map(then)
+        })
+    }
+}
+
+/// Storage part of the config file, e.g. `{"backend": "sqlite", "file": "./assets/thesis.sqlite"}`
+/// or `{"backend": "mysql", "host": "db.school.local", "user": "game", "database": "thesis"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    Mysql(MysqlConfig),
+    Sqlite {
+        file: String,
+    },
+    /// Nothing is written to disk, everything is gone when the game closes
+    Memory,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig::Mysql(MysqlConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChallengeResult, GameStore, LeaderboardEntry};
+
+    const FEN: &str = "1 2 pC 1";
+
+    /// Saves players, tutorial levels and prefabs whose names have quotes and
+    /// characters outside ASCII and checks they are read back unchanged. The
+    /// suffix keeps the names apart on a database earlier runs wrote to.
+    pub fn names_round_trip(store: &mut dyn GameStore, suffix: &str) {
+        let names: Vec<String> = [
+            "O'Brien",
+            "\"Quoted\" name",
+            "back\\slash",
+            "Пётр Иванов",
+            "玩家 🎲",
+        ]
+        .iter()
+        .map(|name| format!("{name} {suffix}"))
+        .collect();
+        for name in &names {
+            store.add_player(name).unwrap();
+            store.save_tutorial_level(name, FEN).unwrap();
+            store.save_challenge_prefab(name, FEN).unwrap();
+        }
+
+        let players = store.player_names().unwrap();
+        let levels: Vec<String> = store
+            .levels_for_player(0)
+            .unwrap()
+            .into_iter()
+            .map(|level| level.level_description)
+            .collect();
+        let prefabs: Vec<String> = store
+            .challenge_prefabs()
+            .unwrap()
+            .into_iter()
+            .map(|prefab| prefab.level_name)
+            .collect();
+        for name in &names {
+            assert!(players.contains(name), "player {name} was not read back");
+            assert!(levels.contains(name), "level {name} was not read back");
+            assert!(prefabs.contains(name), "prefab {name} was not read back");
+        }
+    }
+
+    /// Ranks five players on a prefab, one of them without a result, and reads
+    /// the leaderboard back a page at a time. Results on another prefab and
+    /// worse attempts of a player do not count.
+    pub fn leaderboard_ranks_and_pages(store: &mut dyn GameStore, suffix: &str) {
+        let prefab = format!("leaderboard {suffix}");
+        let other_prefab = format!("other {suffix}");
+        store.save_challenge_prefab(&prefab, FEN).unwrap();
+        store.save_challenge_prefab(&other_prefab, FEN).unwrap();
+        let prefab_ids: Vec<i32> = [&prefab, &other_prefab]
+            .iter()
+            .map(|name| {
+                store
+                    .challenge_prefabs()
+                    .unwrap()
+                    .into_iter()
+                    .find(|saved| &&saved.level_name == name)
+                    .unwrap()
+                    .prefab_id
+            })
+            .collect();
+        let (prefab_id, other_prefab_id) = (prefab_ids[0], prefab_ids[1]);
+
+        let names: Vec<String> = ["Anna", "Boris", "Vera", "Gleb", "Dina"]
+            .iter()
+            .map(|name| format!("{name} {suffix}"))
+            .collect();
+        let ids: Vec<i32> = names
+            .iter()
+            .map(|name| store.add_player(name).unwrap())
+            .collect();
+        let results = [
+            (ids[0], prefab_id, 6),
+            (ids[0], prefab_id, 4),
+            (ids[1], prefab_id, 5),
+            (ids[2], prefab_id, 5),
+            (ids[3], prefab_id, 7),
+            (ids[3], other_prefab_id, 1),
+        ];
+        for (attempt_id, &(player_id, prefab_id, num_of_steps)) in results.iter().enumerate() {
+            let result = ChallengeResult {
+                player_id,
+                fen: FEN.to_string(),
+                num_of_steps,
+                prefab_id,
+                board_seed: None,
+                optimal_steps: None,
+            };
+            store
+                .save_challenge_result(attempt_id as u64, &result)
+                .unwrap();
+        }
+
+        let ranks = |entries: Vec<LeaderboardEntry>| -> Vec<(u32, String, i32)> {
+            entries
+                .into_iter()
+                .map(|entry| (entry.rank, entry.player_name, entry.num_of_steps))
+                .collect()
+        };
+        let expected = vec![
+            (1, names[0].clone(), 4),
+            (2, names[1].clone(), 5),
+            (2, names[2].clone(), 5),
+            (4, names[3].clone(), 7),
+        ];
+        assert_eq!(
+            ranks(store.leaderboard(prefab_id, 0, 10).unwrap()),
+            expected
+        );
+        assert_eq!(store.leaderboard_size(prefab_id).unwrap(), 4);
+        assert_eq!(
+            ranks(store.leaderboard(prefab_id, 1, 2).unwrap()),
+            expected[1..3]
+        );
+        assert_eq!(
+            ranks(store.leaderboard(prefab_id, 3, 2).unwrap()),
+            expected[3..]
+        );
+        assert!(store.leaderboard(prefab_id, 4, 2).unwrap().is_empty());
+
+        let standing = |store: &mut dyn GameStore, player_id| {
+            store
+                .player_standing(prefab_id, player_id)
+                .unwrap()
+                .map(|standing| (standing.rank, standing.personal_best))
+        };
+        assert_eq!(standing(store, ids[0]), Some((1, 4)));
+        assert_eq!(standing(store, ids[2]), Some((2, 5)));
+        assert_eq!(standing(store, ids[3]), Some((4, 7)));
+        assert_eq!(standing(store, ids[4]), None);
+    }
+}
@@ -0,0 +1,446 @@
+use std::{env, error::Error, time::Duration};
+
+use mysql::{prelude::Queryable, OptsBuilder, Params, Pool, PooledConn, TxOpts};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    migrations::{pending_migrations, SCHEMA_VERSION_TABLE},
+    AllLevelsWithSolutions, ChallengeResult, ChallengeScore, FenPrefab, HintUsage,
+    LeaderboardEntry, LevelStore, PlayerStanding, ScoreStore, StoreResult,
+};
+
+const HOST_VAR: &str = "THESIS_DB_HOST";
+const PORT_VAR: &str = "THESIS_DB_PORT";
+const USER_VAR: &str = "THESIS_DB_USER";
+const PASSWORD_VAR: &str = "THESIS_DB_PASSWORD";
+const DATABASE_VAR: &str = "THESIS_DB_NAME";
+const DEFAULT_PORT: u16 = 3306;
+/// An unreachable host should not keep the game at a black screen for long
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the classroom database is. Every field can also be set by an
+/// environment variable, which wins over the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MysqlConfig {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub database: Option<String>,
+}
+
+impl MysqlConfig {
+    /// The settings with the environment variables applied on top.
+    pub fn with_env(&self) -> MysqlConfig {
+        let var = |name| env::var(name).ok();
+        MysqlConfig {
+            host: var(HOST_VAR).or_else(|| self.host.clone()),
+            port: var(PORT_VAR)
+                .and_then(|port| port.parse().ok())
+                .or(self.port),
+            user: var(USER_VAR).or_else(|| self.user.clone()),
+            password: var(PASSWORD_VAR).or_else(|| self.password.clone()),
+            database: var(DATABASE_VAR).or_else(|| self.database.clone()),
+        }
+    }
+}
+
+/// The shared classroom database.
+pub struct MysqlStore {
+    pool: Pool,
+}
+
+impl MysqlStore {
+    pub fn connect(config: &MysqlConfig) -> Result<MysqlStore, Box<dyn Error>> {
+        let host = config
+            .host
+            .clone()
+            .ok_or_else(|| format!("no database host, set it in the config or in {HOST_VAR}"))?;
+        let opts = OptsBuilder::new()
+            .ip_or_hostname(Some(host))
+            .tcp_port(config.port.unwrap_or(DEFAULT_PORT))
+            .user(config.user.clone())
+            .pass(config.password.clone())
+            .db_name(config.database.clone())
+            .tcp_connect_timeout(Some(CONNECT_TIMEOUT));
+        let pool = Pool::new(opts)?;
+        // Fails here rather than on the first query when the database is unreachable
+        let mut conn = pool.get_conn()?;
+        migrate(&mut conn)?;
+        Ok(MysqlStore { pool })
+    }
+
+    /// A connection from the pool. The pool checks it first, so after the network
+    /// dropped a connection the next call opens a new one.
+    fn conn(&self) -> Result<PooledConn, mysql::Error> {
+        self.pool.get_conn()
+    }
+
+    /// Runs one statement in its own transaction. Values are always bound as
+    /// parameters, never spliced into the query text.
+    fn execute(&mut self, query: &str, params: impl Into<Params>) -> StoreResult<()> {
+        let mut conn = self.conn()?;
+        let mut transaction = conn.start_transaction(TxOpts::default())?;
+        transaction.exec_drop(query, params)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn scores(
+        &mut self,
+        query: &str,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>> {
+        let scores = self.conn()?.exec_map(
+            query,
+            (player_id, limit),
+            |(prefab_id, level_name, num_of_steps): (i32, Option<String>, i32)| ChallengeScore {
+                prefab_id,
+                level_name: level_name.unwrap_or_default(),
+                num_of_steps,
+            },
+        )?;
+        Ok(scores)
+    }
+}
+
+/// Errors of the network or the connection pool, as opposed to errors the server
+/// returned for one query.
+pub fn is_connection_error(err: &(dyn Error + Send + Sync + 'static)) -> bool {
+    matches!(
+        err.downcast_ref::<mysql::Error>(),
+        Some(mysql::Error::IoError(_) | mysql::Error::DriverError(_) | mysql::Error::CodecError(_))
+    )
+}
+
+/// Applies every migration the database does not have yet. MySQL commits
+/// `CREATE` and `ALTER` statements on its own, so migrations are written to be
+/// safe to run again after one failed halfway.
+fn migrate(conn: &mut PooledConn) -> Result<(), Box<dyn Error>> {
+    conn.query_drop(SCHEMA_VERSION_TABLE)?;
+    let version: Option<u32> =
+        conn.query_first("SELECT COALESCE(MAX(version), 0) FROM schema_version;")?;
+    for migration in pending_migrations(version.unwrap_or(0))? {
+        conn.query_drop(migration.mysql)?;
+        conn.exec_drop(
+            "INSERT INTO schema_version (version, description) VALUES (?, ?);",
+            (migration.version, migration.description),
+        )?;
+    }
+    Ok(())
+}
+
+impl LevelStore for MysqlStore {
+    fn levels_for_player(&mut self, player_id: i32) -> StoreResult<Vec<AllLevelsWithSolutions>> {
+        let solved_levels_query = r"SELECT tl.id, tl.descrip, tl.fen, tls.number_of_steps
+            FROM tutorial_levels tl
+            RIGHT JOIN tutorial_level_solutions tls ON tl.id = tls.level_id
+            WHERE tls.player_id = ?
+            ORDER BY tl.id;";
+
+        let unsolved_levels_query = r"SELECT DISTINCT tl.id, tl.descrip, tl.fen
+            FROM tutorial_levels tl
+            LEFT JOIN tutorial_level_solutions tls ON tl.id = tls.level_id
+            WHERE tl.id NOT IN (SELECT tl.id FROM tutorial_levels tl
+            RIGHT JOIN tutorial_level_solutions tls ON tl.id = tls.level_id
+            WHERE tls.player_id = ?)
+            ORDER BY tl.id;";
+
+        let mut conn = self.conn()?;
+        let mut transaction = conn.start_transaction(TxOpts::default())?;
+        let mut all_levels = transaction.exec_map(
+            solved_levels_query,
+            (player_id,),
+            |(level_id, level_description, fen, number_of_steps)| AllLevelsWithSolutions {
+                level_id,
+                level_description,
+                fen,
+                number_of_steps,
+            },
+        )?;
+        let mut unsolved_levels = transaction.exec_map(
+            unsolved_levels_query,
+            (player_id,),
+            |(level_id, level_description, fen)| AllLevelsWithSolutions {
+                level_id,
+                level_description,
+                fen,
+                number_of_steps: None,
+            },
+        )?;
+        all_levels.append(&mut unsolved_levels);
+        transaction.commit()?;
+        Ok(all_levels)
+    }
+
+    fn save_tutorial_level(&mut self, description: &str, fen: &str) -> StoreResult<()> {
+        self.execute(
+            "INSERT INTO tutorial_levels (descrip, fen) VALUES (?, ?);",
+            (description, fen),
+        )
+    }
+
+    fn challenge_prefabs(&mut self) -> StoreResult<Vec<FenPrefab>> {
+        let prefabs = self.conn()?.query_map(
+            "SELECT id, fen, level_name FROM challenge_prefabs ORDER BY id;",
+            |(id, fen_prefab, level_name)| FenPrefab {
+                prefab_id: id,
+                fen: fen_prefab,
+                level_name,
+            },
+        )?;
+        Ok(prefabs)
+    }
+
+    fn challenge_prefab(&mut self, prefab_id: i32) -> StoreResult<Option<String>> {
+        let prefab = self.conn()?.exec_first(
+            "SELECT fen FROM challenge_prefabs WHERE id = ?;",
+            (prefab_id,),
+        )?;
+        Ok(prefab)
+    }
+
+    fn save_challenge_prefab(&mut self, level_name: &str, prefab: &str) -> StoreResult<()> {
+        self.execute(
+            "INSERT INTO challenge_prefabs (fen, level_name) VALUES (?, ?);",
+            (prefab, level_name),
+        )
+    }
+}
+
+impl ScoreStore for MysqlStore {
+    fn player_names(&mut self) -> StoreResult<Vec<String>> {
+        Ok(self.conn()?.query("SELECT player_name FROM players;")?)
+    }
+
+    fn add_player(&mut self, name: &str) -> StoreResult<i32> {
+        let mut conn = self.conn()?;
+        let mut transaction = conn.start_transaction(TxOpts::default())?;
+        transaction.exec_drop("INSERT INTO players (player_name) VALUES (?);", (name,))?;
+        let new_player = transaction.last_insert_id().map(|id| id as i32);
+        transaction.commit()?;
+        Ok(new_player.ok_or("Player id not received")?)
+    }
+
+    fn update_tutorial_score(
+        &mut self,
+        player_id: i32,
+        level_id: i32,
+        steps: i32,
+    ) -> StoreResult<()> {
+        let check_query = r"SELECT COUNT(tls.id) FROM tutorial_level_solutions tls
+            WHERE tls.level_id = ? AND player_id = ?;";
+        let get_score_query = r"SELECT number_of_steps FROM tutorial_level_solutions tls
+            WHERE tls.level_id = ? AND player_id = ?;";
+        let update_query = r"UPDATE tutorial_level_solutions
+            SET number_of_steps = ?
+            WHERE level_id = ? AND player_id = ?;";
+        let insert_query = r"INSERT INTO tutorial_level_solutions
+            (player_id, level_id, number_of_steps)
+            VALUES (?, ?, ?);";
+
+        let mut conn = self.conn()?;
+        let mut transaction = conn.start_transaction(TxOpts::default())?;
+        let is_already_solved: Option<i32> =
+            transaction.exec_first(check_query, (level_id, player_id))?;
+        if is_already_solved.ok_or("No result from query")? == 0 {
+            // Not previously solved
+            transaction.exec_drop(insert_query, (player_id, level_id, steps))?;
+        } else {
+            // Previously solved
+            let prev_result: Option<i32> =
+                transaction.exec_first(get_score_query, (level_id, player_id))?;
+            if prev_result.ok_or("Previous score should be in databse")? > steps {
+                transaction.exec_drop(update_query, (steps, level_id, player_id))?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn save_challenge_result(
+        &mut self,
+        attempt_id: u64,
+        result: &ChallengeResult,
+    ) -> StoreResult<()> {
+        let ChallengeResult {
+            player_id,
+            fen,
+            num_of_steps,
+            prefab_id,
+            board_seed,
+            optimal_steps,
+        } = result;
+        // `None` is bound as NULL
+        let seed = board_seed.map(|board_seed| board_seed.seed);
+        let difficulty = board_seed.map(|board_seed| board_seed.difficulty.code().to_string());
+        self.execute(
+            r"INSERT IGNORE INTO challenge_solutions
+            (fen, num_of_steps, player_id, prefab_id, seed, difficulty, optimal_steps, attempt_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?);",
+            (
+                fen,
+                num_of_steps,
+                player_id,
+                prefab_id,
+                seed,
+                difficulty,
+                optimal_steps,
+                attempt_id,
+            ),
+        )
+    }
+
+    fn save_multiplayer_result(
+        &mut self,
+        attempt_id: u64,
+        player_id: i32,
+        fen: &str,
+        num_of_steps: i32,
+        prefab_id: i32,
+    ) -> StoreResult<()> {
+        self.execute(
+            r"INSERT IGNORE INTO multiplayer_solutions
+            (fen, num_of_steps, player_id, prefab_id, attempt_id)
+            VALUES (?, ?, ?, ?, ?);",
+            (fen, num_of_steps, player_id, prefab_id, attempt_id),
+        )
+    }
+
+    fn save_hint_usage(&mut self, usage: &HintUsage) -> StoreResult<()> {
+        let HintUsage {
+            player_id,
+            level_id,
+            pack_level,
+            game_mode,
+            fen,
+            script_length,
+        } = usage;
+        self.execute(
+            r"INSERT INTO hint_usage
+            (player_id, level_id, pack_level, game_mode, fen, script_length)
+            VALUES (?, ?, ?, ?, ?, ?);",
+            (
+                player_id,
+                level_id,
+                pack_level,
+                game_mode,
+                fen,
+                *script_length as u64,
+            ),
+        )
+    }
+
+    fn best_challenge_scores(
+        &mut self,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>> {
+        self.scores(
+            r"SELECT cs.prefab_id, cp.level_name, cs.num_of_steps FROM challenge_solutions cs
+            LEFT JOIN challenge_prefabs cp ON cs.prefab_id = cp.id
+            WHERE cs.player_id = ?
+            ORDER BY cs.num_of_steps, cs.id
+            LIMIT ?;",
+            player_id,
+            limit,
+        )
+    }
+
+    fn best_multiplayer_scores(
+        &mut self,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>> {
+        self.scores(
+            r"SELECT ms.prefab_id, cp.level_name, ms.num_of_steps FROM multiplayer_solutions ms
+            LEFT JOIN challenge_prefabs cp ON ms.prefab_id = cp.id
+            WHERE ms.player_id = ?
+            ORDER BY ms.num_of_steps, ms.id
+            LIMIT ?;",
+            player_id,
+            limit,
+        )
+    }
+
+    fn leaderboard(
+        &mut self,
+        prefab_id: i32,
+        offset: u32,
+        limit: u32,
+    ) -> StoreResult<Vec<LeaderboardEntry>> {
+        // A player's rank is one more than the number of players who did better
+        let entries = self.conn()?.exec_map(
+            r"SELECT best.player_id, p.player_name, best.steps,
+            (SELECT COUNT(*) + 1 FROM
+                (SELECT MIN(num_of_steps) AS steps FROM challenge_solutions
+                WHERE prefab_id = ? GROUP BY player_id) better
+            WHERE better.steps < best.steps) AS player_rank
+            FROM (SELECT player_id, MIN(num_of_steps) AS steps FROM challenge_solutions
+            WHERE prefab_id = ? GROUP BY player_id) best
+            LEFT JOIN players p ON best.player_id = p.id
+            ORDER BY best.steps, best.player_id
+            LIMIT ? OFFSET ?;",
+            (prefab_id, prefab_id, limit, offset),
+            |(player_id, player_name, num_of_steps, rank): (i32, Option<String>, i32, u32)| {
+                LeaderboardEntry {
+                    rank,
+                    player_id,
+                    player_name: player_name.unwrap_or_default(),
+                    num_of_steps,
+                }
+            },
+        )?;
+        Ok(entries)
+    }
+
+    fn leaderboard_size(&mut self, prefab_id: i32) -> StoreResult<u32> {
+        let size: Option<u32> = self.conn()?.exec_first(
+            "SELECT COUNT(DISTINCT player_id) FROM challenge_solutions WHERE prefab_id = ?;",
+            (prefab_id,),
+        )?;
+        Ok(size.unwrap_or(0))
+    }
+
+    fn player_standing(
+        &mut self,
+        prefab_id: i32,
+        player_id: i32,
+    ) -> StoreResult<Option<PlayerStanding>> {
+        let standing: Option<(i32, u32)> = self.conn()?.exec_first(
+            r"SELECT best.steps,
+            (SELECT COUNT(*) + 1 FROM
+                (SELECT MIN(num_of_steps) AS steps FROM challenge_solutions
+                WHERE prefab_id = ? GROUP BY player_id) better
+            WHERE better.steps < best.steps)
+            FROM (SELECT MIN(num_of_steps) AS steps FROM challenge_solutions
+            WHERE prefab_id = ? AND player_id = ?) best
+            WHERE best.steps IS NOT NULL;",
+            (prefab_id, prefab_id, player_id),
+        )?;
+        Ok(standing.map(|(personal_best, rank)| PlayerStanding {
+            rank,
+            personal_best,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::utilities::storage::tests::names_round_trip;
+
+    /// Needs a test database set up through the environment, e.g.
+    /// `THESIS_DB_HOST=localhost THESIS_DB_NAME=thesis_test cargo test -- --ignored`.
+    #[test]
+    #[ignore = "needs a MySQL database, set THESIS_DB_HOST and run with --ignored"]
+    fn names_with_quotes_and_unicode_round_trip() {
+        let mut store = MysqlStore::connect(&MysqlConfig::default().with_env()).unwrap();
+        let suffix = rand::thread_rng().gen::<u32>().to_string();
+        names_round_trip(&mut store, &suffix);
+    }
+}
@@ -0,0 +1,359 @@
+use std::error::Error;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{
+    migrations::{pending_migrations, SCHEMA_VERSION_TABLE},
+    AllLevelsWithSolutions, ChallengeResult, ChallengeScore, FenPrefab, HintUsage,
+    LeaderboardEntry, LevelStore, PlayerStanding, ScoreStore, StoreResult,
+};
+use crate::model::game_model::generator::BoardSeed;
+
+/// A database file on the player's machine, for classrooms without a network.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Opens the file, creating it when it does not exist, and brings its
+    /// tables up to date.
+    pub fn open(file: &str) -> Result<SqliteStore, Box<dyn Error>> {
+        let mut conn = Connection::open(file)?;
+        migrate(&mut conn)?;
+        Ok(SqliteStore { conn })
+    }
+
+    fn scores(
+        &mut self,
+        query: &str,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>> {
+        let mut statement = self.conn.prepare(query)?;
+        let scores = statement
+            .query_map(params![player_id, limit], |row| {
+                Ok(ChallengeScore {
+                    prefab_id: row.get(0)?,
+                    level_name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    num_of_steps: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(scores)
+    }
+}
+
+/// Applies every migration the file does not have yet, each in its own transaction.
+fn migrate(conn: &mut Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute_batch(SCHEMA_VERSION_TABLE)?;
+    let version: u32 = conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version;",
+        [],
+        |row| row.get(0),
+    )?;
+    for migration in pending_migrations(version)? {
+        let transaction = conn.transaction()?;
+        transaction.execute_batch(migration.sqlite)?;
+        transaction.execute(
+            "INSERT INTO schema_version (version, description) VALUES (?1, ?2);",
+            params![migration.version, migration.description],
+        )?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+impl LevelStore for SqliteStore {
+    fn levels_for_player(&mut self, player_id: i32) -> StoreResult<Vec<AllLevelsWithSolutions>> {
+        let mut statement = self.conn.prepare(
+            r"SELECT tl.id, tl.descrip, tl.fen, MIN(tls.number_of_steps)
+            FROM tutorial_levels tl
+            LEFT JOIN tutorial_level_solutions tls
+            ON tl.id = tls.level_id AND tls.player_id = ?1
+            GROUP BY tl.id
+            ORDER BY MIN(tls.number_of_steps) IS NULL, tl.id;",
+        )?;
+        let levels = statement
+            .query_map(params![player_id], |row| {
+                Ok(AllLevelsWithSolutions {
+                    level_id: row.get(0)?,
+                    level_description: row.get(1)?,
+                    fen: row.get(2)?,
+                    number_of_steps: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(levels)
+    }
+
+    fn save_tutorial_level(&mut self, description: &str, fen: &str) -> StoreResult<()> {
+        self.conn.execute(
+            "INSERT INTO tutorial_levels (descrip, fen) VALUES (?1, ?2);",
+            params![description, fen],
+        )?;
+        Ok(())
+    }
+
+    fn challenge_prefabs(&mut self) -> StoreResult<Vec<FenPrefab>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT id, fen, level_name FROM challenge_prefabs ORDER BY id;")?;
+        let prefabs = statement
+            .query_map([], |row| {
+                Ok(FenPrefab {
+                    prefab_id: row.get(0)?,
+                    fen: row.get(1)?,
+                    level_name: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(prefabs)
+    }
+
+    fn challenge_prefab(&mut self, prefab_id: i32) -> StoreResult<Option<String>> {
+        let prefab = self
+            .conn
+            .query_row(
+                "SELECT fen FROM challenge_prefabs WHERE id = ?1;",
+                params![prefab_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(prefab)
+    }
+
+    fn save_challenge_prefab(&mut self, level_name: &str, prefab: &str) -> StoreResult<()> {
+        self.conn.execute(
+            "INSERT INTO challenge_prefabs (fen, level_name) VALUES (?1, ?2);",
+            params![prefab, level_name],
+        )?;
+        Ok(())
+    }
+}
+
+impl ScoreStore for SqliteStore {
+    fn player_names(&mut self) -> StoreResult<Vec<String>> {
+        let mut statement = self.conn.prepare("SELECT player_name FROM players;")?;
+        let names = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(names)
+    }
+
+    fn add_player(&mut self, name: &str) -> StoreResult<i32> {
+        let conn = &self.conn;
+        conn.execute(
+            "INSERT INTO players (player_name) VALUES (?1);",
+            params![name],
+        )?;
+        Ok(conn.last_insert_rowid() as i32)
+    }
+
+    fn update_tutorial_score(
+        &mut self,
+        player_id: i32,
+        level_id: i32,
+        steps: i32,
+    ) -> StoreResult<()> {
+        let conn = &self.conn;
+        let updated = conn.execute(
+            r"UPDATE tutorial_level_solutions SET number_of_steps = ?3
+            WHERE player_id = ?1 AND level_id = ?2 AND number_of_steps > ?3;",
+            params![player_id, level_id, steps],
+        )?;
+        if updated == 0 {
+            conn.execute(
+                r"INSERT INTO tutorial_level_solutions (player_id, level_id, number_of_steps)
+                SELECT ?1, ?2, ?3 WHERE NOT EXISTS (SELECT 1 FROM tutorial_level_solutions
+                WHERE player_id = ?1 AND level_id = ?2);",
+                params![player_id, level_id, steps],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn save_challenge_result(
+        &mut self,
+        attempt_id: u64,
+        result: &ChallengeResult,
+    ) -> StoreResult<()> {
+        let (seed, difficulty) = match result.board_seed {
+            Some(BoardSeed { seed, difficulty }) => {
+                (Some(seed as i64), Some(difficulty.code().to_string()))
+            }
+            None => (None, None),
+        };
+        self.conn.execute(
+            r"INSERT INTO challenge_solutions
+            (fen, num_of_steps, player_id, prefab_id, seed, difficulty, optimal_steps, attempt_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT (attempt_id) DO NOTHING;",
+            params![
+                result.fen,
+                result.num_of_steps,
+                result.player_id,
+                result.prefab_id,
+                seed,
+                difficulty,
+                result.optimal_steps,
+                // SQLite integers are signed, the bits are kept as they are
+                attempt_id as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn save_multiplayer_result(
+        &mut self,
+        attempt_id: u64,
+        player_id: i32,
+        fen: &str,
+        num_of_steps: i32,
+        prefab_id: i32,
+    ) -> StoreResult<()> {
+        self.conn.execute(
+            r"INSERT INTO multiplayer_solutions
+            (fen, num_of_steps, player_id, prefab_id, attempt_id)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT (attempt_id) DO NOTHING;",
+            params![fen, num_of_steps, player_id, prefab_id, attempt_id as i64],
+        )?;
+        Ok(())
+    }
+
+    fn save_hint_usage(&mut self, usage: &HintUsage) -> StoreResult<()> {
+        self.conn.execute(
+            r"INSERT INTO hint_usage
+            (player_id, level_id, pack_level, game_mode, fen, script_length)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6);",
+            params![
+                usage.player_id,
+                usage.level_id,
+                usage.pack_level,
+                usage.game_mode,
+                usage.fen,
+                usage.script_length as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn best_challenge_scores(
+        &mut self,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>> {
+        self.scores(
+            r"SELECT cs.prefab_id, cp.level_name, cs.num_of_steps FROM challenge_solutions cs
+            LEFT JOIN challenge_prefabs cp ON cs.prefab_id = cp.id
+            WHERE cs.player_id = ?1
+            ORDER BY cs.num_of_steps, cs.id
+            LIMIT ?2;",
+            player_id,
+            limit,
+        )
+    }
+
+    fn best_multiplayer_scores(
+        &mut self,
+        player_id: i32,
+        limit: u32,
+    ) -> StoreResult<Vec<ChallengeScore>> {
+        self.scores(
+            r"SELECT ms.prefab_id, cp.level_name, ms.num_of_steps FROM multiplayer_solutions ms
+            LEFT JOIN challenge_prefabs cp ON ms.prefab_id = cp.id
+            WHERE ms.player_id = ?1
+            ORDER BY ms.num_of_steps, ms.id
+            LIMIT ?2;",
+            player_id,
+            limit,
+        )
+    }
+
+    fn leaderboard(
+        &mut self,
+        prefab_id: i32,
+        offset: u32,
+        limit: u32,
+    ) -> StoreResult<Vec<LeaderboardEntry>> {
+        // A player's rank is one more than the number of players who did better
+        let mut statement = self.conn.prepare(
+            r"SELECT best.player_id, p.player_name, best.steps,
+            (SELECT COUNT(*) + 1 FROM
+                (SELECT MIN(num_of_steps) AS steps FROM challenge_solutions
+                WHERE prefab_id = ?1 GROUP BY player_id) better
+            WHERE better.steps < best.steps)
+            FROM (SELECT player_id, MIN(num_of_steps) AS steps FROM challenge_solutions
+            WHERE prefab_id = ?1 GROUP BY player_id) best
+            LEFT JOIN players p ON best.player_id = p.id
+            ORDER BY best.steps, best.player_id
+            LIMIT ?2 OFFSET ?3;",
+        )?;
+        let entries = statement
+            .query_map(params![prefab_id, limit, offset], |row| {
+                Ok(LeaderboardEntry {
+                    player_id: row.get(0)?,
+                    player_name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    num_of_steps: row.get(2)?,
+                    rank: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    fn leaderboard_size(&mut self, prefab_id: i32) -> StoreResult<u32> {
+        let size = self.conn.query_row(
+            "SELECT COUNT(DISTINCT player_id) FROM challenge_solutions WHERE prefab_id = ?1;",
+            params![prefab_id],
+            |row| row.get(0),
+        )?;
+        Ok(size)
+    }
+
+    fn player_standing(
+        &mut self,
+        prefab_id: i32,
+        player_id: i32,
+    ) -> StoreResult<Option<PlayerStanding>> {
+        let standing = self
+            .conn
+            .query_row(
+                r"SELECT best.steps,
+                (SELECT COUNT(*) + 1 FROM
+                    (SELECT MIN(num_of_steps) AS steps FROM challenge_solutions
+                    WHERE prefab_id = ?1 GROUP BY player_id) better
+                WHERE better.steps < best.steps)
+                FROM (SELECT MIN(num_of_steps) AS steps FROM challenge_solutions
+                WHERE prefab_id = ?1 AND player_id = ?2) best
+                WHERE best.steps IS NOT NULL;",
+                params![prefab_id, player_id],
+                |row| {
+                    Ok(PlayerStanding {
+                        personal_best: row.get(0)?,
+                        rank: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(standing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utilities::storage::tests::{leaderboard_ranks_and_pages, names_round_trip};
+
+    #[test]
+    fn names_with_quotes_and_unicode_round_trip() {
+        let mut store = SqliteStore::open(":memory:").unwrap();
+        names_round_trip(&mut store, "1");
+    }
+
+    #[test]
+    fn leaderboard_shares_ranks_and_pages() {
+        let mut store = SqliteStore::open(":memory:").unwrap();
+        leaderboard_ranks_and_pages(&mut store, "1");
+    }
+}
@@ -1,7 +1,9 @@
 use crate::{
     model::game_model::{
         game::Game,
-        pizzle_pieces::{CollectPerlPuzzlePiece, MovementPuzzlePiece, PuzzlePiece},
+        pizzle_pieces::{
+            CollectPerlPuzzlePiece, MovementPuzzlePiece, OpenShellPuzzlePiece, PuzzlePiece,
+        },
     },
     utilities::script_plugin::{ScriptPlugin, ScriptRes},
     view::{image_handler::ImageMap, GameState},
@@ -149,6 +151,61 @@ pub fn create_collect_perl_puzzle_piece_entity(
     (entity, format!("c{pawn_color}p"))
 }
 
+pub fn create_open_shell_puzzle_piece_entity(
+    commands: &mut Commands,
+    pawn_color: String,
+    script_res: &ScriptRes,
+    image_handler: &ImageMap,
+) -> (Entity, String) {
+    let entity = commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::BLACK,
+                custom_size: Some(Vec2 { x: 100.0, y: 50.0 }),
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(
+                -300.0,
+                300.0 - (script_res.script.len() as f32 * 50.0),
+                0.0,
+            ),
+            ..Default::default()
+        })
+        .insert(OpenShellPuzzlePiece {
+            pawn_color: pawn_color.clone(),
+        })
+        .insert(PuzzlePiece)
+        .with_children(|parent| {
+            parent
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::GREEN,
+                        custom_size: Some(Vec2 { x: 95.0, y: 45.0 }),
+                        ..Default::default()
+                    },
+                    transform: Transform::from_translation(Vec3::Z),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            format!("{pawn_color} opens shell"),
+                            TextStyle {
+                                font: image_handler.2.get(0).unwrap().clone(),
+                                font_size: 20.0,
+                                color: Color::BLACK,
+                            },
+                        )
+                        .with_alignment(TextAlignment::CENTER),
+                        transform: Transform::from_translation(Vec3::Z),
+                        ..Default::default()
+                    });
+                });
+        })
+        .id();
+    (entity, format!("o{pawn_color}s"))
+}
+
 fn delete_puzzle_piece(
     mut commands: Commands,
     windows: Res<Windows>,
@@ -179,6 +236,7 @@ fn delete_puzzle_piece(
                         .expect("Entity should be in the array");
                     commands.entity(entity).despawn_recursive();
                     game.puzzle.remove(result);
+                    game.hint = None;
                     script_res.script.remove(result);
                     game.redraw_cond = RedrawPuzzle::Yes;
                 }
@@ -3,16 +3,25 @@
 use self::LevelControlButtonType::*;
 use super::despawn_screen;
 use crate::{
-    model::game_model::game::{Game, LevelCell},
+    model::game_model::{
+        game::{Game, LevelCell},
+        generator::challenge_code,
+        simulation::{GridPosition, Pawn, Simulation},
+        solver::{solve, STATE_LIMIT},
+        tiles::{tile_spec, TileBehavior},
+    },
     utilities::{
         language_plugin::LanguageResource,
         script_plugin::{reset_level, ScriptRes, ScriptRunStatus},
     },
     view::{image_handler::ImageMap, GameState},
-    MAX_LEVEL_HEIGHT, MAX_LEVEL_WIDTH, SHIFT_DOWN, SHIFT_TO_RIGHT,
+    SHIFT_DOWN, SHIFT_TO_RIGHT,
+};
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
 };
-use bevy::prelude::*;
-use std::cmp::min;
+use futures_lite::future;
 use std::slice::Iter;
 
 const LEVEL_DISPLAY_BUTTON_SIZE: f32 = 50.0;
@@ -26,12 +35,6 @@ pub struct LevelViewPlugin;
 #[derive(Component)]
 pub struct CellCollider;
 
-#[derive(Component)]
-pub struct GreenPawn;
-
-#[derive(Component)]
-pub struct OrangePawn;
-
 #[derive(Component, PartialEq, Eq)]
 pub enum ShellType {
     Closed,
@@ -59,6 +62,10 @@ pub enum LevelControlButtonType {
 #[derive(Component)]
 pub struct ScoreText;
 
+/// Solves the level in the background, big levels can take the solver a while.
+#[derive(Component)]
+struct ParTask(Task<Option<u32>>);
+
 impl LevelControlButtonType {
     pub fn iterator() -> Iter<'static, LevelControlButtonType> {
         static BUTTONTYPES: [LevelControlButtonType; 5] =
@@ -73,7 +80,9 @@ impl Plugin for LevelViewPlugin {
             .add_system_set(
                 SystemSet::on_exit(GameState::Game).with_system(despawn_screen::<LevelView>),
             )
-            .add_system(level_control_button_system);
+            .add_system(level_control_button_system)
+            .add_system(mirror_simulation)
+            .add_system(show_par);
     }
 }
 
@@ -83,11 +92,20 @@ fn create_panel(
     game: Res<Game>,
     image_map: Res<ImageMap>,
     language: Res<LanguageResource>,
+    mut simulation: ResMut<Simulation>,
 ) {
-    let image_size = min(
-        MAX_LEVEL_WIDTH as u32 / game.columns,
-        MAX_LEVEL_HEIGHT as u32 / game.rows,
-    ) as f32;
+    *simulation = Simulation::new(&game);
+    // Pack levels and challenges come with their par, every other level is solved here
+    if game.par.is_none() {
+        let fen = game.to_fen();
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            Game::try_from_fen(&fen)
+                .ok()
+                .and_then(|game| solve(&game, STATE_LIMIT).steps())
+        });
+        commands.spawn((ParTask(task), LevelView));
+    }
+    let image_size = game.cell_size();
     let background = commands
         .spawn((ImageBundle {
             style: Style {
@@ -120,17 +138,13 @@ fn create_panel(
                 .level_matrix
                 .get(i.try_into().unwrap(), j.try_into().unwrap())
                 .unwrap();
-            if cell_data.letter != '_' {
+            if cell_data.spec().behavior() != TileBehavior::Empty {
                 let cell = create_cell(cell_data, &image_map, &mut commands, image_size, i, j);
-                if "ZQWERTYUIASDFGHJKzxcvqwertyuiasdfghjkbnmlBNoO"
-                    .to_string()
-                    .contains(cell_data.letter)
-                {
-                    walls_shells.push(cell);
-                } else if "pPXV".to_string().contains(cell_data.letter) {
-                    pawns_stones.push(cell);
-                } else if cell_data.letter == 'C' {
-                    perls.push(cell);
+                match cell_data.spec().behavior() {
+                    TileBehavior::Collider | TileBehavior::Shell { .. } => walls_shells.push(cell),
+                    TileBehavior::Pawn | TileBehavior::Movable => pawns_stones.push(cell),
+                    TileBehavior::Pearl => perls.push(cell),
+                    TileBehavior::Empty => {}
                 }
             }
         }
@@ -149,6 +163,7 @@ fn create_panel(
         &mut commands,
         &image_map,
         language.game.perls_score_label.clone(),
+        level_details(&game, &language),
     );
     commands
         .entity(background)
@@ -157,6 +172,42 @@ fn create_panel(
         .add_child(info_panel);
 }
 
+/// Par and challenge code shown after the perl score, the par is "-" while it
+/// is solved and when the solver gave up.
+fn level_details(game: &Game, language: &LanguageResource) -> String {
+    let par = match game.par {
+        Some(par) => par.to_string(),
+        None => "-".to_string(),
+    };
+    let mut details = format!("   {}: {par}", language.game.par_label);
+    if let Some(seed) = game.seed {
+        details.push_str(&format!(
+            "   {} {}",
+            language.main_menu.challenge_code_label,
+            challenge_code(game.level_id, seed)
+        ));
+    }
+    details
+}
+
+fn show_par(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut ParTask)>,
+    mut score_text: Query<&mut Text, With<ScoreText>>,
+    mut game: ResMut<Game>,
+    language: Res<LanguageResource>,
+) {
+    for (entity, mut task) in &mut tasks {
+        if let Some(par) = future::block_on(future::poll_once(&mut task.0)) {
+            commands.entity(entity).despawn();
+            game.par = par;
+            for mut text in &mut score_text {
+                text.sections[2].value = level_details(&game, &language);
+            }
+        }
+    }
+}
+
 fn create_cell(
     mut cell_data: LevelCell,
     image_map: &ImageMap,
@@ -166,6 +217,10 @@ fn create_cell(
     j: u32,
 ) -> Entity {
     let img = get_image(cell_data.letter, image_map);
+    let grid_position = GridPosition {
+        row: i as usize,
+        column: j as usize,
+    };
     let cell = commands
         .spawn(ImageBundle {
             style: Style {
@@ -174,48 +229,60 @@ fn create_cell(
                     height: Val::Px(cell_data.image_size_y),
                 },
                 position_type: PositionType::Absolute,
-                position: UiRect {
-                    left: Val::Px(
-                        image_size * (j) as f32 + cell_data.extra_move_x + SHIFT_TO_RIGHT,
-                    ),
-                    top: Val::Px(image_size * (i) as f32 + cell_data.extra_move_y + SHIFT_DOWN),
-                    ..default()
-                },
+                position: cell_ui_position(&cell_data, grid_position, image_size),
                 ..Default::default()
             },
             transform: Transform::from_rotation(Quat::from_rotation_z(cell_data.angle)),
             image: img,
             ..Default::default()
         })
+        .insert(grid_position)
         .id();
-    if "pP".to_string().contains(cell_data.letter) {
-        if "p".to_string().contains(cell_data.letter) {
-            commands.entity(cell).insert(GreenPawn);
-        } else {
-            commands.entity(cell).insert(OrangePawn);
+    match cell_data.spec().behavior() {
+        TileBehavior::Pawn => {
+            let color = cell_data.pawn.expect("Pawn cells carry their colour");
+            commands.entity(cell).insert((
+                Pawn {
+                    id: color.id(),
+                    color,
+                },
+                UiImage(image_map.3.get(&color).unwrap().0.clone()),
+                BackgroundColor(color.tint()),
+            ));
+        }
+        TileBehavior::Movable => {
+            commands.entity(cell).insert(CellMovable);
+        }
+        TileBehavior::Shell { open: false } => {
+            commands.entity(cell).insert(ShellType::Closed);
         }
-    } else if "XV".to_string().contains(cell_data.letter) {
-        commands.entity(cell).insert(CellMovable);
-    } else if "o".to_string().contains(cell_data.letter) {
-        commands.entity(cell).insert(ShellType::Closed);
-    } else if "O".to_string().contains(cell_data.letter) {
-        commands.entity(cell).insert(ShellType::Open);
-    } else if "C".to_string().contains(cell_data.letter) {
-        commands.entity(cell).insert(Perl::NotCollected);
-    } else {
-        commands.entity(cell).insert(CellCollider);
+        TileBehavior::Shell { open: true } => {
+            commands.entity(cell).insert(ShellType::Open);
+        }
+        TileBehavior::Pearl => {
+            commands.entity(cell).insert(Perl::NotCollected);
+        }
+        TileBehavior::Collider => {
+            commands.entity(cell).insert(CellCollider);
+        }
+        TileBehavior::Empty => {}
     }
     cell_data.cell_entity = Some(cell);
     cell
 }
 
-fn get_image(letter: char, image_map: &ImageMap) -> UiImage {
-    for key in (image_map).0.keys() {
-        if key.contains(letter) {
-            return (*image_map.0.get(key).unwrap()).clone();
-        }
+pub fn cell_ui_position(cell_data: &LevelCell, position: GridPosition, image_size: f32) -> UiRect {
+    UiRect {
+        left: Val::Px(
+            image_size * position.column as f32 + cell_data.extra_move_x + SHIFT_TO_RIGHT,
+        ),
+        top: Val::Px(image_size * position.row as f32 + cell_data.extra_move_y + SHIFT_DOWN),
+        ..default()
     }
-    UiImage::default()
+}
+
+fn get_image(letter: char, image_map: &ImageMap) -> UiImage {
+    image_map.0.get(&letter).cloned().unwrap_or_default()
 }
 
 fn create_button_panel(commands: &mut Commands, image_map: &ImageMap) -> Entity {
@@ -280,7 +347,12 @@ fn create_button(
         .id();
 }
 
-fn create_info_panel(commands: &mut Commands, image_map: &ImageMap, perl_label: String) -> Entity {
+fn create_info_panel(
+    commands: &mut Commands,
+    image_map: &ImageMap,
+    perl_label: String,
+    details: String,
+) -> Entity {
     commands
         .spawn((
             TextBundle::from_sections([
@@ -297,6 +369,14 @@ fn create_info_panel(commands: &mut Commands, image_map: &ImageMap, perl_label:
                     font_size: 40.0,
                     color: Color::BLACK,
                 }),
+                TextSection::new(
+                    details,
+                    TextStyle {
+                        font: image_map.2.get(0).unwrap().clone(),
+                        font_size: 40.0,
+                        color: Color::BLACK,
+                    },
+                ),
             ])
             .with_style(Style {
                 position_type: PositionType::Absolute,
@@ -358,3 +438,78 @@ fn level_control_button_system(
         }
     }
 }
+
+fn mirror_simulation(
+    simulation: Res<Simulation>,
+    game: Res<Game>,
+    image_map: Res<ImageMap>,
+    mut pawns: Query<(&mut Style, &Pawn, &GridPosition)>,
+    mut perls: Query<(&mut Style, &mut Perl, &GridPosition), Without<Pawn>>,
+    mut stones: Query<
+        (&mut Style, &GridPosition),
+        (With<CellMovable>, Without<Pawn>, Without<Perl>),
+    >,
+    mut shells: Query<
+        (
+            &mut Style,
+            &mut UiImage,
+            &mut BackgroundColor,
+            &mut ShellType,
+            &GridPosition,
+        ),
+        (Without<Pawn>, Without<Perl>, Without<CellMovable>),
+    >,
+    mut score_text: Query<&mut Text, With<ScoreText>>,
+) {
+    for mut text in &mut score_text {
+        text.sections[1].value =
+            format!("{}/{}", simulation.collected_perls(), game.required_perls);
+    }
+    if !simulation.is_changed() {
+        return;
+    }
+    let image_size = game.cell_size();
+    let cell_at =
+        |origin: &GridPosition| *game.level_matrix.get(origin.row, origin.column).unwrap();
+    for (mut style, pawn, origin) in &mut pawns {
+        if let Some(position) = simulation.pawn_position(pawn.color) {
+            style.position = cell_ui_position(&cell_at(origin), position, image_size);
+        }
+    }
+    for (mut style, mut perl, position) in &mut perls {
+        if simulation.is_perl_collected(*position) {
+            *perl = Perl::Collected;
+            style.display = Display::None;
+        } else {
+            *perl = Perl::NotCollected;
+            style.display = Display::Flex;
+        }
+    }
+    for (mut style, origin) in &mut stones {
+        if let Some(position) = simulation.stone_position(*origin) {
+            style.position = cell_ui_position(&cell_at(origin), position, image_size);
+        }
+    }
+    for (mut style, mut image, mut color, mut shell_type, position) in &mut shells {
+        let letter = if simulation.is_shell_open(*position) {
+            *shell_type = ShellType::Open;
+            'O'
+        } else {
+            *shell_type = ShellType::Closed;
+            'o'
+        };
+        let cell_data = tile_spec(letter).unwrap().level_cell(image_size);
+        style.size = Size {
+            width: Val::Px(cell_data.image_size_x),
+            height: Val::Px(cell_data.image_size_y),
+        };
+        style.position = cell_ui_position(&cell_data, *position, image_size);
+        image.0 = image_map.0.get(&letter).unwrap().0.clone();
+        // Shell art always shows a pearl, so an emptied shell is greyed out
+        *color = if simulation.shell_has_pearl(*position) {
+            BackgroundColor::default()
+        } else {
+            Color::GRAY.into()
+        };
+    }
+}
@@ -1,29 +1,38 @@
 #![allow(clippy::type_complexity)]
+#![allow(clippy::too_many_arguments)]
 
-use std::borrow::BorrowMut;
-
-use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use bevy::{
+    ecs::schedule::ShouldRun,
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use futures_lite::future;
 
 use super::{
     despawn_screen,
     game_view_plugin::{RedrawPuzzle, BLOCK_TYPE_BUTTON_HEIGHT},
     puzzle_pieces_panels::{
-        clean_up_panel, close_puzzle_piece_panel, create_pawn_actions_panel, spawn_block,
-        PuzzlePiecePanel,
+        clean_up_panel, close_puzzle_piece_panel, create_pawn_actions_panel, highlight_hint,
+        spawn_block, PuzzlePiecePanel,
     },
 };
 use crate::{
-    model::game_model::game::{Game, GameCompleted, GameMode},
+    model::game_model::{
+        game::{Game, GameCompleted, GameMode},
+        pizzle_pieces::PuzzlePiece,
+        simulation::Simulation,
+        solver::{hint, Hint, STATE_LIMIT},
+    },
     utilities::{
-        database_plugin::{
-            save_challenge_result, save_multiplayer_result, update_score_for_tutorial_level,
-            ConfigResource, DatabaseConnection,
-        },
+        database_plugin::ConfigResource,
         language_plugin::LanguageResource,
+        level_pack_plugin::PackProgress,
         network_plugin::{
             ConnectionType, GameStage, NetworkResource, SendScoreToClient, SendScoreToServer,
         },
+        score_queue_plugin::{save_score, ScoreQueue, ScoreWrite},
         script_plugin::{reset_level, ScriptRes},
+        storage::{ChallengeResult, HintUsage, Storage},
     },
     view::{image_handler::ImageMap, GameState},
 };
@@ -53,6 +62,23 @@ pub enum PuzzleMovementButtons {
 #[derive(Component)]
 pub struct CompleteLevelButton;
 
+#[derive(Component)]
+pub struct HintButton;
+
+#[derive(Component)]
+struct HintsLeftText;
+
+/// Looks for a hint in the background, the solver can take a while on big levels.
+/// `script` is what the hint was asked for, a hint for an older script is dropped.
+#[derive(Component)]
+struct HintTask {
+    task: Task<Hint>,
+    script: Vec<String>,
+}
+
+/// Sent when the hint button produced a hint that should be shown.
+pub struct ShowHint(pub Hint);
+
 #[derive(Resource, Default)]
 pub struct HidingPanel {
     pub panel: Option<Entity>,
@@ -77,11 +103,24 @@ impl Plugin for MenuViewPlugin {
                     .with_system(complete_game_button),
             )
             .add_system(no_save_exit)
+            .add_system(hint_button)
+            .add_system(take_hint)
+            .add_system(show_hint)
+            .add_system(highlight_hint)
+            .add_event::<ShowHint>()
             .init_resource::<HidingPanel>();
     }
 }
 
-fn create_panel(mut commands: Commands, image_map: Res<ImageMap>, language: Res<LanguageResource>) {
+fn create_panel(
+    mut commands: Commands,
+    image_map: Res<ImageMap>,
+    language: Res<LanguageResource>,
+    game: Res<Game>,
+    config: Res<ConfigResource>,
+    pack_progress: Res<PackProgress>,
+) {
+    let hints_left = hints_left(&game, &config, &pack_progress);
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -256,6 +295,43 @@ fn create_panel(mut commands: Commands, image_map: Res<ImageMap>, language: Res<
                     .with_text_alignment(TextAlignment::CENTER),));
                 })
                 .insert(CompleteLevelButton);
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(80.0), Val::Px(30.0)),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::all(Val::Px(15.0)),
+                        ..default()
+                    },
+                    background_color: Color::AQUAMARINE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_sections([
+                            TextSection::new(
+                                language.game.hint_button.clone(),
+                                TextStyle {
+                                    font: image_map.2.get(0).unwrap().clone(),
+                                    font_size: 20.0,
+                                    color: Color::BLACK,
+                                },
+                            ),
+                            TextSection::new(
+                                format!(" ({hints_left})"),
+                                TextStyle {
+                                    font: image_map.2.get(0).unwrap().clone(),
+                                    font_size: 20.0,
+                                    color: Color::BLACK,
+                                },
+                            ),
+                        ])
+                        .with_text_alignment(TextAlignment::CENTER),
+                        HintsLeftText,
+                    ));
+                })
+                .insert(HintButton);
             parent
                 .spawn(ButtonBundle {
                     style: Style {
@@ -297,6 +373,7 @@ fn puzzle_type_buttons(
     >,
     mut panel: Query<Entity, With<PuzzlePiecePanel>>,
     language: Res<LanguageResource>,
+    simulation: Res<Simulation>,
 ) {
     for (interaction, button_name, mut color) in &mut interaction_query {
         match *interaction {
@@ -309,6 +386,7 @@ fn puzzle_type_buttons(
                         &mut commands,
                         &image_handler,
                         &language.game.pawn_action_panel,
+                        &simulation,
                     );
                 }
                 *color = BackgroundColor(Color::YELLOW);
@@ -393,57 +471,72 @@ fn complete_game_button(
             With<CompleteLevelButton>,
         ),
     >,
+    mut commands: Commands,
     game: Res<Game>,
-    mut db_conn: ResMut<DatabaseConnection>,
+    storage: Option<Res<Storage>>,
     mut game_state: ResMut<State<GameState>>,
     config: Res<ConfigResource>,
     mut network_res: ResMut<NetworkResource>,
     mut event_sender: EventWriter<SendScoreToClient>,
+    mut pack_progress: ResMut<PackProgress>,
+    mut score_queue: ResMut<ScoreQueue>,
 ) {
     for (interaction, mut color) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 *color = BackgroundColor(Color::YELLOW);
+                // The main menu only starts a level once a player exists
+                let player_id = match config.selected_player() {
+                    Some(player) => player.id,
+                    None => continue,
+                };
                 if game.game_mode == GameMode::Tutorial {
-                    update_score_for_tutorial_level(
-                        db_conn.borrow_mut(),
-                        config
-                            .local_players
-                            .get(config.selected_player_id as usize)
-                            .unwrap()
-                            .id,
-                        game.level_id,
-                        game.solution,
-                    );
+                    match &game.pack_level {
+                        Some(pack_level) => {
+                            pack_progress.update_score(player_id, pack_level, game.solution)
+                        }
+                        None => save_score(
+                            &mut commands,
+                            storage.as_deref(),
+                            &mut score_queue,
+                            ScoreWrite::Tutorial {
+                                player_id,
+                                level_id: game.level_id,
+                                steps: game.solution,
+                            },
+                        ),
+                    }
                     game_state.set(GameState::LevelSelector).unwrap();
                 }
                 if game.game_mode == GameMode::Challenge {
-                    save_challenge_result(
-                        &mut db_conn,
-                        config
-                            .local_players
-                            .get(config.selected_player_id as usize)
-                            .unwrap()
-                            .id,
-                        game.fen.clone(),
-                        game.solution,
-                        game.level_id,
+                    save_score(
+                        &mut commands,
+                        storage.as_deref(),
+                        &mut score_queue,
+                        ScoreWrite::Challenge(ChallengeResult {
+                            player_id,
+                            fen: game.to_fen(),
+                            num_of_steps: game.solution,
+                            prefab_id: game.level_id,
+                            board_seed: game.seed,
+                            optimal_steps: game.par,
+                        }),
                     );
                     game_state.set(GameState::MainMenu).unwrap();
                 }
                 if game.game_mode == GameMode::Multiplayer {
                     network_res.my_game_score.complete(game.solution);
                     network_res.game_stage = GameStage::End;
-                    save_multiplayer_result(
-                        &mut db_conn,
-                        config
-                            .local_players
-                            .get(config.selected_player_id as usize)
-                            .unwrap()
-                            .id,
-                        game.fen.clone(),
-                        game.solution,
-                        game.level_id,
+                    save_score(
+                        &mut commands,
+                        storage.as_deref(),
+                        &mut score_queue,
+                        ScoreWrite::Multiplayer {
+                            player_id,
+                            fen: game.to_fen(),
+                            num_of_steps: game.solution,
+                            prefab_id: game.level_id,
+                        },
                     );
                     event_sender.send(SendScoreToClient::default());
                     game_state.set(GameState::Multiplayer).unwrap();
@@ -459,6 +552,155 @@ fn complete_game_button(
     }
 }
 
+fn hint_button(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<HintButton>),
+    >,
+    pending: Query<(), With<HintTask>>,
+    game: Res<Game>,
+    script_res: Res<ScriptRes>,
+    config: Res<ConfigResource>,
+    pack_progress: Res<PackProgress>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = BackgroundColor(Color::YELLOW);
+                if hints_left(&game, &config, &pack_progress) == 0 || !pending.is_empty() {
+                    continue;
+                }
+                let fen = game.to_fen();
+                let script = script_res.script.clone();
+                let task = AsyncComputeTaskPool::get().spawn(async move {
+                    match Game::try_from_fen(&fen) {
+                        Ok(game) => hint(&game, &script, STATE_LIMIT),
+                        Err(_) => Hint::Unknown,
+                    }
+                });
+                commands.spawn((
+                    HintTask {
+                        task,
+                        script: script_res.script.clone(),
+                    },
+                    MenuView,
+                ));
+            }
+            Interaction::Hovered => {
+                *color = BackgroundColor(Color::AQUAMARINE);
+            }
+            Interaction::None => {
+                *color = BackgroundColor(Color::AQUAMARINE);
+            }
+        }
+    }
+}
+
+/// Hints the selected player has left on the level. They are counted per level,
+/// so leaving and opening the level again does not give them back.
+fn hints_left(game: &Game, config: &ConfigResource, pack_progress: &PackProgress) -> u32 {
+    let used = config.selected_player().map_or(0, |player| {
+        pack_progress.hints_used(player.id, &game.hint_key())
+    });
+    game.hints_left(used)
+}
+
+/// Counts and records the hint once the solver found it and hands it to `show_hint`.
+/// The use of the hint goes through the score queue, so it is not lost offline.
+fn take_hint(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut HintTask)>,
+    mut game: ResMut<Game>,
+    script_res: Res<ScriptRes>,
+    storage: Option<Res<Storage>>,
+    config: Res<ConfigResource>,
+    mut pack_progress: ResMut<PackProgress>,
+    mut score_queue: ResMut<ScoreQueue>,
+    mut hints_left_text: Query<&mut Text, With<HintsLeftText>>,
+    mut event_sender: EventWriter<ShowHint>,
+) {
+    for (entity, mut hint_task) in &mut tasks {
+        let hint = match future::block_on(future::poll_once(&mut hint_task.task)) {
+            Some(hint) => hint,
+            None => continue,
+        };
+        commands.entity(entity).despawn();
+        // The script was changed while the solver ran, the hint would point at
+        // the wrong blocks. It is not counted, the player can ask again.
+        if script_res.script != hint_task.script {
+            continue;
+        }
+        let player = match config.selected_player() {
+            Some(player) => player,
+            None => continue,
+        };
+        // Only hints that tell the player something new are counted
+        if matches!(hint, Hint::Next(_) | Hint::RemoveFrom(_)) {
+            pack_progress.add_hint(player.id, &game.hint_key());
+            save_score(
+                &mut commands,
+                storage.as_deref(),
+                &mut score_queue,
+                ScoreWrite::Hint(HintUsage {
+                    player_id: player.id,
+                    level_id: game.pack_level.is_none().then_some(game.level_id),
+                    pack_level: game.pack_level.clone(),
+                    game_mode: game.game_mode.name().to_string(),
+                    fen: game.to_fen(),
+                    script_length: hint_task.script.len(),
+                }),
+            );
+            let hints_left = hints_left(&game, &config, &pack_progress);
+            for mut text in &mut hints_left_text {
+                text.sections[1].value = format!(" ({hints_left})");
+            }
+        }
+        game.hint = match hint {
+            Hint::Next(instruction) => Some(instruction),
+            _ => None,
+        };
+        event_sender.send(ShowHint(hint));
+    }
+}
+
+/// Opens the pawn actions panel for a suggested block, or marks the blocks
+/// that have to go in red.
+fn show_hint(
+    mut commands: Commands,
+    mut event_reader: EventReader<ShowHint>,
+    image_handler: Res<ImageMap>,
+    language: Res<LanguageResource>,
+    simulation: Res<Simulation>,
+    game: Res<Game>,
+    panel: Query<Entity, With<PuzzlePiecePanel>>,
+    mut puzzle_pieces: Query<&mut Sprite, With<PuzzlePiece>>,
+) {
+    for ShowHint(hint) in event_reader.iter() {
+        match *hint {
+            Hint::Next(_) => {
+                for p in &panel {
+                    commands.entity(p).despawn_recursive();
+                }
+                create_pawn_actions_panel(
+                    &mut commands,
+                    &image_handler,
+                    &language.game.pawn_action_panel,
+                    &simulation,
+                );
+            }
+            Hint::RemoveFrom(index) => {
+                for entity in game.puzzle.iter().skip(index) {
+                    if let Ok(mut sprite) = puzzle_pieces.get_mut(*entity) {
+                        sprite.color = Color::RED;
+                    }
+                }
+            }
+            Hint::Done | Hint::Unknown => {}
+        }
+    }
+}
+
 fn no_save_exit(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
@@ -3,18 +3,25 @@
 use bevy::prelude::*;
 
 use crate::{
-    model::game_model::game::Game,
+    model::game_model::{
+        game::Game,
+        simulation::{Direction, Instruction, Simulation},
+    },
     utilities::{language_plugin::PuzzleButtonPanel, script_plugin::ScriptRes},
     view::image_handler::ImageMap,
 };
 
 use super::game_view_plugin::{
     create_collect_perl_puzzle_piece_entity, create_move_puzzle_piece_entity,
-    BLOCK_TYPE_BUTTON_HEIGHT,
+    create_open_shell_puzzle_piece_entity, BLOCK_TYPE_BUTTON_HEIGHT,
 };
 
-const PAWNS: [&str; 2] = ["green", "orange"];
-const DIRECTIONS: [&str; 4] = ["up", "right", "down", "left"];
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
 
 #[derive(Component)]
 pub struct PuzzlePieceButton;
@@ -25,11 +32,15 @@ pub struct ClosePuzzlePiecePanelButton;
 #[derive(Component)]
 pub struct PuzzlePiecePanel;
 
+/// Builds the block palette for the pawns present on the level. Shell blocks
+/// are only offered when the level has a shell.
 pub fn create_pawn_actions_panel(
     commands: &mut Commands,
     image_handler: &ImageMap,
     language: &PuzzleButtonPanel,
+    simulation: &Simulation,
 ) {
+    let pawns = simulation.pawn_colors();
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -71,83 +82,46 @@ pub fn create_pawn_actions_panel(
                     ..Default::default()
                 }),
             );
-            let mut i: usize = 0;
-            for color in PAWNS {
-                for direction in DIRECTIONS {
-                    parent
-                        .spawn(ButtonBundle {
-                            style: Style {
-                                size: Size {
-                                    width: Val::Percent(90.0),
-                                    height: Val::Px(BLOCK_TYPE_BUTTON_HEIGHT),
-                                },
-                                margin: UiRect {
-                                    left: Val::Px(5.0),
-                                    right: Val::Px(5.0),
-                                    top: Val::Px(5.0),
-                                    bottom: Val::Px(5.0),
-                                },
-                                ..default()
-                            },
-                            background_color: Color::AQUAMARINE.into(),
-                            ..default()
-                        })
-                        .with_children(|button| {
-                            button.spawn(
-                                (TextBundle::from_section(
-                                    language.buttons[i].clone(),
-                                    TextStyle {
-                                        font: image_handler.2.get(0).unwrap().clone(),
-                                        font_size: 20.0,
-                                        color: Color::BLACK,
-                                    },
-                                ))
-                                .with_text_alignment(TextAlignment::CENTER),
-                            );
-                        })
-                        .insert(Name::new(format!("move {color} {direction}")))
-                        .insert(PuzzlePieceButton);
-                    i += 1;
+            for pawn in &pawns {
+                for (i, direction) in DIRECTIONS.into_iter().enumerate() {
+                    let text = language
+                        .move_button
+                        .replace("{pawn}", &language.pawn_names[pawn.id()])
+                        .replace("{direction}", &language.directions[i]);
+                    spawn_action_button(
+                        parent,
+                        image_handler,
+                        text,
+                        Instruction::Move {
+                            pawn: *pawn,
+                            direction,
+                        },
+                    );
                 }
             }
-            for color in PAWNS {
-                parent
-                    .spawn(ButtonBundle {
-                        style: Style {
-                            size: Size {
-                                width: Val::Percent(90.0),
-                                height: Val::Px(BLOCK_TYPE_BUTTON_HEIGHT),
-                            },
-                            margin: UiRect {
-                                left: Val::Px(5.0),
-                                right: Val::Px(5.0),
-                                top: Val::Px(5.0),
-                                bottom: Val::Px(5.0),
-                            },
-                            ..default()
-                        },
-                        background_color: Color::AQUAMARINE.into(),
-                        ..default()
-                    })
-                    .with_children(|button| {
-                        button.spawn(
-                            (TextBundle::from_section(
-                                language.buttons[i].clone(),
-                                TextStyle {
-                                    font: image_handler.2.get(0).unwrap().clone(),
-                                    font_size: 20.0,
-                                    color: Color::BLACK,
-                                },
-                            ))
-                            .with_text_alignment(TextAlignment::CENTER),
-                        );
-                    })
-                    .insert(Name::new(format!(
-                        "{color
-                    } collects perl"
-                    )))
-                    .insert(PuzzlePieceButton);
-                i += 1;
+            for pawn in &pawns {
+                let text = language
+                    .collect_button
+                    .replace("{pawn}", &language.pawn_actor_names[pawn.id()]);
+                spawn_action_button(
+                    parent,
+                    image_handler,
+                    text,
+                    Instruction::CollectPerl { pawn: *pawn },
+                );
+            }
+            if simulation.has_shells() {
+                for pawn in &pawns {
+                    let text = language
+                        .open_shell_button
+                        .replace("{pawn}", &language.pawn_actor_names[pawn.id()]);
+                    spawn_action_button(
+                        parent,
+                        image_handler,
+                        text,
+                        Instruction::OpenShell { pawn: *pawn },
+                    );
+                }
             }
             parent
                 .spawn(ButtonBundle {
@@ -190,6 +164,48 @@ pub fn create_pawn_actions_panel(
         .insert(PuzzlePiecePanel);
 }
 
+fn spawn_action_button(
+    parent: &mut ChildBuilder,
+    image_handler: &ImageMap,
+    text: String,
+    instruction: Instruction,
+) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size {
+                    width: Val::Percent(90.0),
+                    height: Val::Px(BLOCK_TYPE_BUTTON_HEIGHT),
+                },
+                margin: UiRect {
+                    left: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    top: Val::Px(5.0),
+                    bottom: Val::Px(5.0),
+                },
+                ..default()
+            },
+            background_color: Color::AQUAMARINE.into(),
+            ..default()
+        })
+        .with_children(|button| {
+            button.spawn(
+                (TextBundle::from_section(
+                    text.clone(),
+                    TextStyle {
+                        font: image_handler.2.get(0).unwrap().clone(),
+                        font_size: 20.0,
+                        color: Color::BLACK,
+                    },
+                ))
+                .with_text_alignment(TextAlignment::CENTER),
+            );
+        })
+        .insert(Name::new(text))
+        .insert(instruction)
+        .insert(PuzzlePieceButton);
+}
+
 pub fn close_puzzle_piece_panel(
     mut commands: Commands,
     mut interaction_query: Query<
@@ -223,127 +239,40 @@ pub fn close_puzzle_piece_panel(
 pub fn spawn_block(
     mut commands: Commands,
     mut interaction_query: Query<
-        (&Interaction, &Name, &mut BackgroundColor),
+        (&Interaction, &Instruction, &mut BackgroundColor),
         (Changed<Interaction>, With<Button>, With<PuzzlePieceButton>),
     >,
     mut script_res: ResMut<ScriptRes>,
     mut game: ResMut<Game>,
     image_handler: Res<ImageMap>,
 ) {
-    for (interaction, name, mut color) in &mut interaction_query {
+    for (interaction, instruction, mut color) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
-                match name.as_str() {
-                    "move green up" => {
-                        let (entity, string) = create_move_puzzle_piece_entity(
-                            &mut commands,
-                            "u".to_string(),
-                            "g".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "move green down" => {
-                        let (entity, string) = create_move_puzzle_piece_entity(
-                            &mut commands,
-                            "d".to_string(),
-                            "g".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "move green left" => {
-                        let (entity, string) = create_move_puzzle_piece_entity(
-                            &mut commands,
-                            "l".to_string(),
-                            "g".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "move green right" => {
-                        let (entity, string) = create_move_puzzle_piece_entity(
-                            &mut commands,
-                            "r".to_string(),
-                            "g".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "move orange up" => {
-                        let (entity, string) = create_move_puzzle_piece_entity(
-                            &mut commands,
-                            "u".to_string(),
-                            "o".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "move orange down" => {
-                        let (entity, string) = create_move_puzzle_piece_entity(
-                            &mut commands,
-                            "d".to_string(),
-                            "o".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "move orange left" => {
-                        let (entity, string) = create_move_puzzle_piece_entity(
-                            &mut commands,
-                            "l".to_string(),
-                            "o".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "move orange right" => {
-                        let (entity, string) = create_move_puzzle_piece_entity(
-                            &mut commands,
-                            "r".to_string(),
-                            "o".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "green collects perl" => {
-                        let (entity, string) = create_collect_perl_puzzle_piece_entity(
-                            &mut commands,
-                            "g".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    "orange collects perl" => {
-                        let (entity, string) = create_collect_perl_puzzle_piece_entity(
-                            &mut commands,
-                            "o".to_string(),
-                            &script_res,
-                            &image_handler,
-                        );
-                        game.puzzle.push(entity);
-                        script_res.script.push(string);
-                    }
-                    _ => {}
+                let (entity, string) = match *instruction {
+                    Instruction::Move { pawn, direction } => create_move_puzzle_piece_entity(
+                        &mut commands,
+                        direction.code().to_string(),
+                        pawn.code().to_string(),
+                        &script_res,
+                        &image_handler,
+                    ),
+                    Instruction::CollectPerl { pawn } => create_collect_perl_puzzle_piece_entity(
+                        &mut commands,
+                        pawn.code().to_string(),
+                        &script_res,
+                        &image_handler,
+                    ),
+                    Instruction::OpenShell { pawn } => create_open_shell_puzzle_piece_entity(
+                        &mut commands,
+                        pawn.code().to_string(),
+                        &script_res,
+                        &image_handler,
+                    ),
                 };
+                game.puzzle.push(entity);
+                game.hint = None;
+                script_res.script.push(string);
                 *color = BackgroundColor(Color::YELLOW);
             }
             Interaction::Hovered => {
@@ -356,6 +285,28 @@ pub fn spawn_block(
     }
 }
 
+const HINT_COLOR: Color = Color::GOLD;
+
+/// Keeps the block suggested by the last hint highlighted while it is not hovered.
+pub fn highlight_hint(
+    game: Res<Game>,
+    mut buttons: Query<
+        (&Interaction, &Instruction, &mut BackgroundColor),
+        (With<Button>, With<PuzzlePieceButton>),
+    >,
+) {
+    for (interaction, instruction, mut color) in &mut buttons {
+        if *interaction != Interaction::None {
+            continue;
+        }
+        if game.hint == Some(*instruction) {
+            *color = BackgroundColor(HINT_COLOR);
+        } else if color.0 == HINT_COLOR {
+            *color = BackgroundColor(Color::AQUAMARINE);
+        }
+    }
+}
+
 pub fn clean_up_panel(mut commands: Commands, mut panel: Query<Entity, With<PuzzlePiecePanel>>) {
     for p in &mut panel {
         commands.entity(p).despawn_recursive();
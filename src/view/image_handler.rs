@@ -1,3 +1,4 @@
+use crate::model::game_model::{simulation::PawnColor, tiles::TILES};
 use bevy::prelude::*;
 use std::collections::HashMap;
 
@@ -5,9 +6,10 @@ pub struct ImageHandlerPlugin;
 
 #[derive(Resource)]
 pub struct ImageMap(
-    pub HashMap<String, UiImage>,
+    pub HashMap<char, UiImage>,
     pub Vec<UiImage>,
     pub Vec<Handle<Font>>,
+    pub HashMap<PawnColor, UiImage>,
 );
 
 impl Plugin for ImageHandlerPlugin {
@@ -18,91 +20,18 @@ impl Plugin for ImageHandlerPlugin {
 
 fn load_images(mut commands: Commands, asset_server: Res<AssetServer>) {
     // Cells
-    let mut image_map: HashMap<String, UiImage> = HashMap::new();
-
-    // Big blocks
-    image_map.insert(
-        "Z".to_string(),
-        UiImage(asset_server.load("blocks/big_block.png")),
-    );
-    image_map.insert(
-        "QWER".to_string(),
-        UiImage(asset_server.load("blocks/big_left_triangle.png")),
-    );
-    image_map.insert(
-        "TYUI".to_string(),
-        UiImage(asset_server.load("blocks/big_right_triangle.png")),
-    );
-    image_map.insert(
-        "ASDF".to_string(),
-        UiImage(asset_server.load("blocks/big_left_half_slope.png")),
-    );
-    image_map.insert(
-        "GHJK".to_string(),
-        UiImage(asset_server.load("blocks/big_right_half_slope.png")),
-    );
-
-    // Small blocks
-    image_map.insert(
-        "zxcv".to_string(),
-        UiImage(asset_server.load("blocks/small_block.png")),
-    );
-    image_map.insert(
-        "qwer".to_string(),
-        UiImage(asset_server.load("blocks/small_left_triangle.png")),
-    );
-    image_map.insert(
-        "tyui".to_string(),
-        UiImage(asset_server.load("blocks/small_right_triangle.png")),
-    );
-    image_map.insert(
-        "asdf".to_string(),
-        UiImage(asset_server.load("blocks/small_left_half_slope.png")),
-    );
-    image_map.insert(
-        "ghjk".to_string(),
-        UiImage(asset_server.load("blocks/small_right_half_slope.png")),
-    );
-    image_map.insert(
-        "bnml".to_string(),
-        UiImage(asset_server.load("blocks/lower_half_block.png")),
-    );
-    image_map.insert(
-        "BN".to_string(),
-        UiImage(asset_server.load("blocks/center_half_block.png")),
-    );
+    let mut image_map: HashMap<char, UiImage> = HashMap::new();
+    for tile in TILES.iter() {
+        if let Some(sprite) = tile.kind.sprite() {
+            image_map.insert(tile.letter, UiImage(asset_server.load(sprite)));
+        }
+    }
 
     // Pawns
-    image_map.insert(
-        "p".to_string(),
-        UiImage(asset_server.load("pawns/green.png")),
-    );
-    image_map.insert(
-        "P".to_string(),
-        UiImage(asset_server.load("pawns/orange.png")),
-    );
-
-    // Extra
-    image_map.insert(
-        "X".to_string(),
-        UiImage(asset_server.load("extra/stone.png")),
-    );
-    image_map.insert(
-        "o".to_string(),
-        UiImage(asset_server.load("extra/closed_shell.png")),
-    );
-    image_map.insert(
-        "O".to_string(),
-        UiImage(asset_server.load("extra/open_shell.png")),
-    );
-    image_map.insert(
-        "C".to_string(),
-        UiImage(asset_server.load("extra/perl.png")),
-    );
-    image_map.insert(
-        "V".to_string(),
-        UiImage(asset_server.load("extra/hexagon.png")),
-    );
+    let mut pawns: HashMap<PawnColor, UiImage> = HashMap::new();
+    for color in PawnColor::ALL {
+        pawns.insert(color, UiImage(asset_server.load(color.sprite())));
+    }
 
     // Buttons
     let buttons: Vec<UiImage> = vec![
@@ -123,5 +52,5 @@ fn load_images(mut commands: Commands, asset_server: Res<AssetServer>) {
         asset_server.load("fonts/NotoSans-SemiBold.ttf"),
     ];
 
-    commands.insert_resource(ImageMap(image_map, buttons, fonts));
+    commands.insert_resource(ImageMap(image_map, buttons, fonts, pawns));
 }
@@ -0,0 +1,986 @@
+#![allow(clippy::type_complexity)]
+#![allow(clippy::too_many_arguments)]
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    ui::FocusPolicy,
+    window::ReceivedCharacter,
+};
+use futures_lite::future;
+
+use crate::{
+    model::game_model::{
+        draft::{DraftCell, DraftKind, LevelDraft},
+        fen::{FenError, PAWN_LETTERS},
+        game::{cell_size, Game},
+        generator::{roll_prefab, Difficulty, RollReport},
+        simulation::PawnColor,
+        solver::{solve, Solution, STATE_LIMIT},
+        tiles::{tile_spec, PERL_LETTER},
+    },
+    utilities::{
+        database_plugin::DbTask,
+        language_plugin::{LanguageResource, LevelEditorText},
+        level_pack_plugin::save_to_editor_pack,
+        storage::{LevelStore, Storage},
+    },
+    view::{despawn_screen, image_handler::ImageMap, GameState},
+};
+
+const EDITOR_FILE_FOLDER: &str = "./assets/levels/";
+const EDITOR_PREFABS_PATH: &str = "./assets/levels/editor_prefabs.txt";
+/// Boards filled per difficulty when a prefab is rolled
+const ROLLS_PER_DIFFICULTY: u32 = 50;
+/// Prefab spots are drawn as faded perls and pawns
+const SPOT_TINT: Color = Color::rgba(1.0, 1.0, 1.0, 0.4);
+const PALETTE_BUTTON_SIZE: f32 = 40.0;
+const COUNTER_BUTTON_SIZE: f32 = 40.0;
+const MAX_LEVEL_NAME_LENGTH: usize = 40;
+const MAX_GOAL: u32 = 99;
+/// The draft is checked once painting pauses for this long, not on every cell
+const CHECK_DELAY_SECONDS: f32 = 0.3;
+
+#[derive(Debug, Component)]
+struct LevelEditorView;
+
+#[derive(Debug, Component)]
+struct EditorGrid;
+
+#[derive(Debug, Component, Clone, Copy)]
+struct EditorCell {
+    row: u32,
+    column: u32,
+}
+
+#[derive(Debug, Component)]
+struct PaletteButton(DraftCell);
+
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+enum EditorButton {
+    RowsDown,
+    RowsUp,
+    ColumnsDown,
+    ColumnsUp,
+    GoalDown,
+    GoalUp,
+    ToggleKind,
+    Roll,
+    SaveFile,
+    SaveDatabase,
+    Back,
+}
+
+#[derive(Debug, Component, Clone, Copy)]
+enum EditorValueText {
+    Rows,
+    Columns,
+    Goal,
+}
+
+#[derive(Debug, Component)]
+struct EditorPalette(DraftKind);
+
+#[derive(Debug, Component)]
+struct EditorKindText;
+
+#[derive(Debug, Component)]
+struct LevelNameText;
+
+#[derive(Debug, Component)]
+struct EditorStatusText;
+
+#[derive(Debug)]
+enum DraftCheck {
+    /// The draft changed and its check has not finished yet
+    Checking,
+    Level(Result<Solution, FenError>),
+    /// The fullest board the prefab can produce has to be a valid level
+    Prefab(Result<(), FenError>),
+}
+
+/// Checks the draft in the background, solving a big level can take a while.
+#[derive(Component)]
+struct DraftCheckTask(Task<DraftCheck>);
+
+#[derive(Resource)]
+struct CheckTimer(Timer);
+
+/// Rolls the prefab in the background, it solves `ROLLS_PER_DIFFICULTY` boards
+/// for every difficulty.
+#[derive(Component)]
+struct RollTask(Task<Vec<(Difficulty, RollReport)>>);
+
+/// Editor state that is not part of the level itself.
+#[derive(Debug, Resource)]
+struct LevelEditorData {
+    brush: DraftCell,
+    level_name: String,
+    /// Result of parsing and solving the draft, refreshed after every change
+    check: DraftCheck,
+    /// Reports of the last roll of the prefab, cleared on every change
+    rolls: Vec<(Difficulty, RollReport)>,
+    message: String,
+}
+
+impl Default for LevelEditorData {
+    fn default() -> Self {
+        LevelEditorData {
+            brush: DraftCell::EMPTY,
+            level_name: String::new(),
+            check: DraftCheck::Checking,
+            rolls: Vec::new(),
+            message: String::new(),
+        }
+    }
+}
+
+pub struct LevelEditorPlugin;
+
+impl Plugin for LevelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::LevelEditor).with_system(init_view))
+            .add_system_set(
+                SystemSet::on_exit(GameState::LevelEditor)
+                    .with_system(despawn_screen::<LevelEditorView>),
+            )
+            .init_resource::<LevelDraft>()
+            .init_resource::<LevelEditorData>()
+            .insert_resource(CheckTimer(Timer::from_seconds(
+                CHECK_DELAY_SECONDS,
+                TimerMode::Once,
+            )))
+            .add_system(editor_buttons)
+            .add_system(palette_buttons)
+            .add_system(paint_cells)
+            .add_system(type_level_name)
+            .add_system(redraw_editor)
+            .add_system(start_draft_check)
+            // A result that finishes in the frame the draft changed has to be
+            // dropped by `redraw_editor`, not written over the new draft
+            .add_system(finish_draft_check.before(redraw_editor))
+            .add_system(finish_roll.before(redraw_editor));
+    }
+}
+
+fn check_draft(kind: DraftKind, fen: &str) -> DraftCheck {
+    match kind {
+        DraftKind::Level => {
+            DraftCheck::Level(Game::try_from_fen(fen).map(|game| solve(&game, STATE_LIMIT)))
+        }
+        DraftKind::Prefab => DraftCheck::Prefab(Game::try_from_fen(fen).map(|_| ())),
+    }
+}
+
+/// Starts the check of the draft once it has not changed for `CHECK_DELAY_SECONDS`.
+fn start_draft_check(
+    mut commands: Commands,
+    mut timer: ResMut<CheckTimer>,
+    pending: Query<(), With<DraftCheckTask>>,
+    draft: Res<LevelDraft>,
+    editor_data: Res<LevelEditorData>,
+    time: Res<Time>,
+    view: Query<(), With<LevelEditorView>>,
+) {
+    timer.0.tick(time.delta());
+    if !timer.0.finished()
+        || view.is_empty()
+        || !pending.is_empty()
+        || !matches!(editor_data.check, DraftCheck::Checking)
+    {
+        return;
+    }
+    let kind = draft.kind;
+    let fen = match kind {
+        DraftKind::Level => draft.to_fen(),
+        DraftKind::Prefab => draft.fullest_fen(),
+    };
+    let task = AsyncComputeTaskPool::get().spawn(async move { check_draft(kind, &fen) });
+    commands.spawn((DraftCheckTask(task), LevelEditorView));
+}
+
+fn finish_roll(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut RollTask)>,
+    mut editor_data: ResMut<LevelEditorData>,
+) {
+    for (entity, mut task) in &mut tasks {
+        if let Some(rolls) = future::block_on(future::poll_once(&mut task.0)) {
+            commands.entity(entity).despawn();
+            editor_data.rolls = rolls;
+            editor_data.message.clear();
+        }
+    }
+}
+
+fn finish_draft_check(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut DraftCheckTask)>,
+    mut editor_data: ResMut<LevelEditorData>,
+) {
+    for (entity, mut task) in &mut tasks {
+        if let Some(check) = future::block_on(future::poll_once(&mut task.0)) {
+            commands.entity(entity).despawn();
+            editor_data.check = check;
+        }
+    }
+}
+
+/// Only named levels the solver can finish, and named prefabs that rolled at
+/// least one solvable board, can be saved.
+fn can_save(editor_data: &LevelEditorData) -> bool {
+    if editor_data.level_name.trim().is_empty() {
+        return false;
+    }
+    match &editor_data.check {
+        DraftCheck::Checking => false,
+        DraftCheck::Level(check) => matches!(check, Ok(Solution::Solved(_))),
+        DraftCheck::Prefab(check) => {
+            check.is_ok()
+                && editor_data
+                    .rolls
+                    .iter()
+                    .any(|(_, report)| report.solvable > 0)
+        }
+    }
+}
+
+fn status_text(
+    editor_data: &LevelEditorData,
+    draft: &LevelDraft,
+    language: &LevelEditorText,
+    difficulty_names: &[String],
+) -> String {
+    let mut lines = vec![match &editor_data.check {
+        DraftCheck::Checking => language.checking_label.clone(),
+        DraftCheck::Level(Err(err)) | DraftCheck::Prefab(Err(err)) => {
+            format!("{}: {err}", language.invalid_label)
+        }
+        DraftCheck::Level(Ok(Solution::Solved(solution))) => language
+            .solvable_label
+            .replace("{steps}", &solution.len().to_string()),
+        DraftCheck::Level(Ok(Solution::Unsolvable { most_perls })) => language
+            .unsolvable_label
+            .replace("{perls}", &most_perls.to_string()),
+        DraftCheck::Level(Ok(Solution::LimitReached)) => language.too_big_label.clone(),
+        DraftCheck::Prefab(Ok(())) => language
+            .spots_label
+            .replace(
+                "{pawn_spots}",
+                &draft.count(DraftCell::PawnSpot).to_string(),
+            )
+            .replace(
+                "{perl_spots}",
+                &draft.count(DraftCell::PerlSpot).to_string(),
+            ),
+    }];
+    for (difficulty, report) in editor_data.rolls.iter() {
+        let name = &difficulty_names[*difficulty as usize];
+        lines.push(match (report.steps.first(), report.steps.last()) {
+            (Some(min), Some(max)) => language
+                .roll_label
+                .replace("{difficulty}", name)
+                .replace("{solvable}", &report.solvable_percent().to_string())
+                .replace("{partly}", &report.partly_solvable_percent().to_string())
+                .replace("{min}", &min.to_string())
+                .replace("{max}", &max.to_string())
+                .replace(
+                    "{median}",
+                    &report.median_steps().unwrap_or_default().to_string(),
+                ),
+            _ => language
+                .no_solvable_rolls_label
+                .replace("{difficulty}", name)
+                .replace("{partly}", &report.partly_solvable_percent().to_string()),
+        });
+    }
+    lines.push(draft.to_fen());
+    lines.push(editor_data.message.clone());
+    lines.join("\n")
+}
+
+fn value_text(value: EditorValueText, draft: &LevelDraft, language: &LevelEditorText) -> String {
+    match (value, draft.kind) {
+        (EditorValueText::Rows, _) => format!("{}: {}", language.rows_label, draft.rows),
+        (EditorValueText::Columns, _) => format!("{}: {}", language.columns_label, draft.columns),
+        (EditorValueText::Goal, DraftKind::Level) => {
+            format!("{}: {}", language.goal_label, draft.goal)
+        }
+        (EditorValueText::Goal, DraftKind::Prefab) => {
+            format!("{}: {}", language.pawns_label, draft.pawns)
+        }
+    }
+}
+
+fn kind_text(kind: DraftKind, language: &LevelEditorText) -> String {
+    match kind {
+        DraftKind::Level => language.level_mode_button.clone(),
+        DraftKind::Prefab => language.prefab_mode_button.clone(),
+    }
+}
+
+fn init_view(
+    mut commands: Commands,
+    image_map: Res<ImageMap>,
+    language: Res<LanguageResource>,
+    draft: Res<LevelDraft>,
+    mut editor_data: ResMut<LevelEditorData>,
+) {
+    editor_data.check = DraftCheck::Checking;
+    editor_data.rolls.clear();
+    editor_data.message.clear();
+    let difficulty_names = &language.main_menu.difficulty_buttons;
+    let language = &language.level_editor;
+    let font = image_map.2.get(0).unwrap().clone();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    ..default()
+                },
+                size: Size {
+                    width: Val::Percent(30.0),
+                    height: Val::Percent(100.0),
+                },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::BEIGE),
+            ..default()
+        })
+        .insert(LevelEditorView)
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    language.title.clone(),
+                    TextStyle {
+                        font: image_map.2.get(1).unwrap().clone(),
+                        font_size: 40.0,
+                        color: Color::BLACK,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                }),
+            );
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(60.0), Val::Px(40.0)),
+                        margin: UiRect::all(Val::Px(5.0)),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::AQUAMARINE),
+                    ..default()
+                })
+                .with_children(|button| {
+                    button.spawn((
+                        TextBundle::from_section(
+                            kind_text(draft.kind, language),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 25.0,
+                                color: Color::BLACK,
+                            },
+                        ),
+                        EditorKindText,
+                    ));
+                })
+                .insert(EditorButton::ToggleKind);
+            for (value, down, up) in [
+                (
+                    EditorValueText::Rows,
+                    EditorButton::RowsDown,
+                    EditorButton::RowsUp,
+                ),
+                (
+                    EditorValueText::Columns,
+                    EditorButton::ColumnsDown,
+                    EditorButton::ColumnsUp,
+                ),
+                (
+                    EditorValueText::Goal,
+                    EditorButton::GoalDown,
+                    EditorButton::GoalUp,
+                ),
+            ] {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(90.0), Val::Px(COUNTER_BUTTON_SIZE)),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::SpaceBetween,
+                            margin: UiRect::all(Val::Px(5.0)),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(Color::WHITE),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        spawn_text_button(row, &font, "-", COUNTER_BUTTON_SIZE, down);
+                        row.spawn((
+                            TextBundle::from_section(
+                                value_text(value, &draft, language),
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 25.0,
+                                    color: Color::BLACK,
+                                },
+                            ),
+                            value,
+                        ));
+                        spawn_text_button(row, &font, "+", COUNTER_BUTTON_SIZE, up);
+                    });
+            }
+            parent.spawn((
+                TextBundle::from_sections([
+                    TextSection::new(
+                        format!("{} ", language.name_label),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 25.0,
+                            color: Color::BLACK,
+                        },
+                    ),
+                    TextSection::new(
+                        editor_data.level_name.clone(),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 25.0,
+                            color: Color::BLACK,
+                        },
+                    ),
+                ])
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                }),
+                LevelNameText,
+            ));
+            parent.spawn(TextBundle::from_section(
+                language.palette_label.clone(),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 25.0,
+                    color: Color::BLACK,
+                },
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(90.0), Val::Auto),
+                        flex_wrap: FlexWrap::Wrap,
+                        margin: UiRect::all(Val::Px(5.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(Color::GRAY),
+                    ..default()
+                })
+                .with_children(|palette| {
+                    spawn_palette_buttons(palette, draft.kind, &image_map);
+                })
+                .insert(EditorPalette(draft.kind));
+            parent.spawn((
+                TextBundle::from_section(
+                    status_text(&editor_data, &draft, language, difficulty_names),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 20.0,
+                        color: Color::BLACK,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::all(Val::Px(5.0)),
+                    max_size: Size::new(Val::Percent(90.0), Val::Undefined),
+                    ..default()
+                }),
+                EditorStatusText,
+            ));
+            for (text, button) in [
+                (&language.roll_button, EditorButton::Roll),
+                (&language.save_file_button, EditorButton::SaveFile),
+                (&language.save_db_button, EditorButton::SaveDatabase),
+                (&language.go_back_button, EditorButton::Back),
+            ] {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(60.0), Val::Px(40.0)),
+                            margin: UiRect::all(Val::Px(5.0)),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            display: roll_display(button, draft.kind),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(Color::AQUAMARINE),
+                        ..default()
+                    })
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            text.clone(),
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 25.0,
+                                color: Color::BLACK,
+                            },
+                        ));
+                    })
+                    .insert(button);
+            }
+        });
+
+    let grid = commands
+        .spawn(NodeBundle {
+            background_color: BackgroundColor(Color::DARK_GRAY),
+            ..default()
+        })
+        .insert((LevelEditorView, EditorGrid))
+        .id();
+    spawn_grid(&mut commands, grid, &draft, &image_map);
+}
+
+/// Rolling only makes sense for prefabs, every other button is always shown.
+fn roll_display(button: EditorButton, kind: DraftKind) -> Display {
+    if button == EditorButton::Roll && kind == DraftKind::Level {
+        Display::None
+    } else {
+        Display::Flex
+    }
+}
+
+fn spawn_palette_buttons(palette: &mut ChildBuilder, kind: DraftKind, image_map: &ImageMap) {
+    for cell in LevelDraft::palette(kind) {
+        palette
+            .spawn(ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(PALETTE_BUTTON_SIZE), Val::Px(PALETTE_BUTTON_SIZE)),
+                    margin: UiRect::all(Val::Px(2.0)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|button| {
+                if cell == DraftCell::EMPTY {
+                    button.spawn(TextBundle::from_section(
+                        "×",
+                        TextStyle {
+                            font: image_map.2.get(0).unwrap().clone(),
+                            font_size: 25.0,
+                            color: Color::BLACK,
+                        },
+                    ));
+                } else {
+                    spawn_cell_image(button, cell, PALETTE_BUTTON_SIZE, image_map);
+                }
+            })
+            .insert(PaletteButton(cell));
+    }
+}
+
+fn spawn_text_button(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    text: &str,
+    size: f32,
+    button: EditorButton,
+) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(size), Val::Px(size)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::AQUAMARINE),
+            ..default()
+        })
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 30.0,
+                    color: Color::BLACK,
+                },
+            ));
+        })
+        .insert(button);
+}
+
+/// Picture of a cell the way the level view draws it: with its footprint and rotation.
+fn spawn_cell_image(parent: &mut ChildBuilder, cell: DraftCell, size: f32, image_map: &ImageMap) {
+    let (letter, image, tint) = match cell {
+        DraftCell::Tile(letter) => match image_map.0.get(&letter) {
+            Some(image) => (letter, image.clone(), Color::WHITE),
+            None => return,
+        },
+        DraftCell::Pawn(color) => (
+            PAWN_LETTERS[0],
+            image_map.3.get(&color).unwrap().clone(),
+            color.tint(),
+        ),
+        DraftCell::PerlSpot => (
+            PERL_LETTER,
+            image_map.0.get(&PERL_LETTER).unwrap().clone(),
+            SPOT_TINT,
+        ),
+        DraftCell::PawnSpot => (
+            PAWN_LETTERS[0],
+            image_map.3.get(&PawnColor::ALL[0]).unwrap().clone(),
+            SPOT_TINT,
+        ),
+    };
+    let level_cell = tile_spec(letter).unwrap().level_cell(size);
+    parent.spawn(ImageBundle {
+        style: Style {
+            size: Size::new(
+                Val::Px(level_cell.image_size_x),
+                Val::Px(level_cell.image_size_y),
+            ),
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(level_cell.extra_move_x),
+                top: Val::Px(level_cell.extra_move_y),
+                ..default()
+            },
+            ..default()
+        },
+        transform: Transform::from_rotation(Quat::from_rotation_z(level_cell.angle)),
+        image,
+        background_color: BackgroundColor(tint),
+        focus_policy: FocusPolicy::Pass,
+        ..default()
+    });
+}
+
+/// Lays out one button per draft cell, sized so the board fits like in the level view.
+fn spawn_grid(commands: &mut Commands, grid: Entity, draft: &LevelDraft, image_map: &ImageMap) {
+    let size = cell_size(draft.rows, draft.columns);
+    commands.entity(grid).insert(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            left: Val::Percent(40.0),
+            top: Val::Px(40.0),
+            ..default()
+        },
+        size: Size::new(
+            Val::Px(size * draft.columns as f32),
+            Val::Px(size * draft.rows as f32),
+        ),
+        ..default()
+    });
+    commands.entity(grid).with_children(|parent| {
+        for row in 0..draft.rows {
+            for column in 0..draft.columns {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(size - 1.0), Val::Px(size - 1.0)),
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: Val::Px(size * column as f32),
+                                top: Val::Px(size * row as f32),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        background_color: BackgroundColor(Color::WHITE),
+                        ..default()
+                    })
+                    .with_children(|button| {
+                        spawn_cell_image(button, draft.cell(row, column), size, image_map);
+                    })
+                    .insert(EditorCell { row, column });
+            }
+        }
+    });
+}
+
+fn redraw_editor(
+    mut commands: Commands,
+    draft: Res<LevelDraft>,
+    mut editor_data: ResMut<LevelEditorData>,
+    mut check_timer: ResMut<CheckTimer>,
+    stale_tasks: Query<Entity, Or<(With<DraftCheckTask>, With<RollTask>)>>,
+    image_map: Res<ImageMap>,
+    language: Res<LanguageResource>,
+    grid: Query<Entity, With<EditorGrid>>,
+    mut palettes: Query<(Entity, &mut EditorPalette)>,
+    mut buttons: Query<(&EditorButton, &mut Style)>,
+    mut value_texts: Query<(&mut Text, &EditorValueText)>,
+    mut kind_texts: Query<&mut Text, (With<EditorKindText>, Without<EditorValueText>)>,
+    mut status_texts: Query<
+        &mut Text,
+        (
+            With<EditorStatusText>,
+            Without<EditorValueText>,
+            Without<EditorKindText>,
+        ),
+    >,
+) {
+    if draft.is_changed() {
+        // Checks and rolls of the old draft are dropped, the new check waits until
+        // painting pauses
+        for task in &stale_tasks {
+            commands.entity(task).despawn();
+        }
+        editor_data.check = DraftCheck::Checking;
+        check_timer.0.reset();
+        editor_data.rolls.clear();
+        for grid in &grid {
+            commands.entity(grid).despawn_descendants();
+            spawn_grid(&mut commands, grid, &draft, &image_map);
+        }
+        for (palette, mut palette_kind) in &mut palettes {
+            if palette_kind.0 == draft.kind {
+                continue;
+            }
+            palette_kind.0 = draft.kind;
+            commands.entity(palette).despawn_descendants();
+            commands.entity(palette).with_children(|palette| {
+                spawn_palette_buttons(palette, draft.kind, &image_map);
+            });
+            if !LevelDraft::palette(draft.kind).contains(&editor_data.brush) {
+                editor_data.brush = DraftCell::EMPTY;
+            }
+        }
+        for (button, mut style) in &mut buttons {
+            style.display = roll_display(*button, draft.kind);
+        }
+        for (mut text, value) in &mut value_texts {
+            text.sections[0].value = value_text(*value, &draft, &language.level_editor);
+        }
+        for mut text in &mut kind_texts {
+            text.sections[0].value = kind_text(draft.kind, &language.level_editor);
+        }
+    }
+    if draft.is_changed() || editor_data.is_changed() {
+        for mut text in &mut status_texts {
+            text.sections[0].value = status_text(
+                &editor_data,
+                &draft,
+                &language.level_editor,
+                &language.main_menu.difficulty_buttons,
+            );
+        }
+    }
+}
+
+fn editor_buttons(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &EditorButton, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut draft: ResMut<LevelDraft>,
+    mut editor_data: ResMut<LevelEditorData>,
+    rolling: Query<(), With<RollTask>>,
+    storage: Option<Res<Storage>>,
+    mut game_state: ResMut<State<GameState>>,
+    language: Res<LanguageResource>,
+) {
+    for (interaction, button, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = BackgroundColor(Color::YELLOW);
+                let (rows, columns) = (draft.rows, draft.columns);
+                match *button {
+                    EditorButton::RowsDown => draft.resize(rows.saturating_sub(1), columns),
+                    EditorButton::RowsUp => draft.resize(rows + 1, columns),
+                    EditorButton::ColumnsDown => draft.resize(rows, columns.saturating_sub(1)),
+                    EditorButton::ColumnsUp => draft.resize(rows, columns + 1),
+                    EditorButton::GoalDown => match draft.kind {
+                        DraftKind::Level => draft.goal = draft.goal.saturating_sub(1),
+                        DraftKind::Prefab => draft.pawns = draft.pawns.saturating_sub(1).max(1),
+                    },
+                    EditorButton::GoalUp => match draft.kind {
+                        DraftKind::Level => draft.goal = (draft.goal + 1).min(MAX_GOAL),
+                        DraftKind::Prefab => {
+                            draft.pawns = (draft.pawns + 1).min(PawnColor::ALL.len() as u32)
+                        }
+                    },
+                    EditorButton::ToggleKind => {
+                        let kind = match draft.kind {
+                            DraftKind::Level => DraftKind::Prefab,
+                            DraftKind::Prefab => DraftKind::Level,
+                        };
+                        draft.set_kind(kind);
+                        editor_data.message.clear();
+                    }
+                    EditorButton::Roll => {
+                        let checked = matches!(editor_data.check, DraftCheck::Prefab(Ok(())));
+                        if checked && rolling.is_empty() {
+                            let prefab = draft.to_fen();
+                            let task = AsyncComputeTaskPool::get().spawn(async move {
+                                Difficulty::ALL
+                                    .into_iter()
+                                    .map(|difficulty| {
+                                        (
+                                            difficulty,
+                                            roll_prefab(&prefab, difficulty, ROLLS_PER_DIFFICULTY),
+                                        )
+                                    })
+                                    .collect()
+                            });
+                            commands.spawn((RollTask(task), LevelEditorView));
+                            editor_data.message = language.level_editor.rolling_label.clone();
+                        }
+                    }
+                    EditorButton::SaveFile | EditorButton::SaveDatabase => {
+                        if !can_save(&editor_data) {
+                            editor_data.message = match draft.kind {
+                                DraftKind::Level => language.level_editor.not_saved_label.clone(),
+                                DraftKind::Prefab => {
+                                    language.level_editor.prefab_not_saved_label.clone()
+                                }
+                            };
+                            continue;
+                        }
+                        let name = editor_data.level_name.trim().to_string();
+                        editor_data.message = if *button == EditorButton::SaveFile {
+                            let saved = match draft.kind {
+                                // Levels go to the level pack of the editor, prefabs to a plain list
+                                DraftKind::Level => {
+                                    let par = match &editor_data.check {
+                                        DraftCheck::Level(Ok(solution)) => solution.steps(),
+                                        _ => None,
+                                    };
+                                    save_to_editor_pack(name, draft.to_fen(), par)
+                                }
+                                DraftKind::Prefab => {
+                                    save_to_file(EDITOR_PREFABS_PATH, &name, &draft.to_fen())
+                                        .map_err(|err| err.into())
+                                }
+                            };
+                            match saved {
+                                Ok(()) => language.level_editor.saved_label.clone(),
+                                Err(err) => err.to_string(),
+                            }
+                        } else if let Some(storage) = storage.as_ref() {
+                            let fen = draft.to_fen();
+                            commands.spawn(match draft.kind {
+                                DraftKind::Level => DbTask::new(storage, move |store| {
+                                    store.save_tutorial_level(&name, &fen)
+                                }),
+                                DraftKind::Prefab => DbTask::new(storage, move |store| {
+                                    store.save_challenge_prefab(&name, &fen)
+                                }),
+                            });
+                            language.level_editor.saved_label.clone()
+                        } else {
+                            language.main_menu.offline_label.clone()
+                        };
+                    }
+                    EditorButton::Back => {
+                        game_state.set(GameState::MainMenu).unwrap();
+                    }
+                }
+            }
+            Interaction::Hovered => {
+                *color = BackgroundColor(Color::WHITE);
+            }
+            Interaction::None => {
+                *color = BackgroundColor(Color::AQUAMARINE);
+            }
+        }
+    }
+}
+
+/// Appends the prefab to an editor file, one "prefab<TAB>name" line each.
+fn save_to_file(path: &str, name: &str, fen: &str) -> std::io::Result<()> {
+    fs::create_dir_all(EDITOR_FILE_FOLDER)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{fen}\t{name}")
+}
+
+fn palette_buttons(
+    mut palette: Query<(&Interaction, &PaletteButton, &mut BackgroundColor)>,
+    mut editor_data: ResMut<LevelEditorData>,
+) {
+    for (interaction, button, _) in &palette {
+        if *interaction == Interaction::Clicked && editor_data.brush != button.0 {
+            editor_data.brush = button.0;
+        }
+    }
+    for (interaction, button, mut color) in &mut palette {
+        let new_color = if editor_data.brush == button.0 {
+            Color::YELLOW
+        } else if *interaction == Interaction::Hovered {
+            Color::AQUAMARINE
+        } else {
+            Color::WHITE
+        };
+        if color.0 != new_color {
+            *color = BackgroundColor(new_color);
+        }
+    }
+}
+
+fn paint_cells(
+    mut cells: Query<
+        (&Interaction, &EditorCell, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut draft: ResMut<LevelDraft>,
+    mut editor_data: ResMut<LevelEditorData>,
+) {
+    for (interaction, cell, mut color) in &mut cells {
+        match *interaction {
+            Interaction::Clicked => {
+                let brush = editor_data.brush;
+                if draft.cell(cell.row, cell.column) != brush {
+                    draft.paint(cell.row, cell.column, brush);
+                    editor_data.message.clear();
+                }
+                *color = BackgroundColor(Color::YELLOW);
+            }
+            Interaction::Hovered => {
+                *color = BackgroundColor(Color::AQUAMARINE);
+            }
+            Interaction::None => {
+                *color = BackgroundColor(Color::WHITE);
+            }
+        }
+    }
+}
+
+fn type_level_name(
+    mut received_characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut editor_data: ResMut<LevelEditorData>,
+    mut level_name_text: Query<&mut Text, With<LevelNameText>>,
+) {
+    if level_name_text.is_empty() {
+        return;
+    }
+    let mut name = editor_data.level_name.clone();
+    for event in received_characters.iter() {
+        if !event.char.is_control() && name.chars().count() < MAX_LEVEL_NAME_LENGTH {
+            name.push(event.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+    if name != editor_data.level_name {
+        for mut text in &mut level_name_text {
+            text.sections[1].value = name.clone();
+        }
+        editor_data.level_name = name;
+    }
+}
@@ -1,18 +1,27 @@
 #![allow(clippy::type_complexity)]
+#![allow(clippy::too_many_arguments)]
 
 use std::borrow::{Borrow, BorrowMut};
 
 use crate::model::game_model::game::GameMode;
 use crate::utilities::database_plugin::{
-    get_all_levels_for_player, AllLevelsWithSolutions, ConfigResource,
+    load_levels_for_player, ConfigResource, DbResult, PlayerLevels,
 };
 use crate::utilities::language_plugin::LanguageResource;
+use crate::utilities::level_pack_plugin::{load_level_packs, PackProgress};
+use crate::utilities::storage::AllLevelsWithSolutions;
 use crate::{
     model::game_model::game::Game,
-    utilities::{database_plugin::DatabaseConnection, script_plugin::ScriptRes},
-    view::{despawn_screen, image_handler::ImageMap, GameState},
+    utilities::{script_plugin::ScriptRes, storage::Storage},
+    view::{
+        despawn_screen,
+        image_handler::ImageMap,
+        loading_spinner::{despawn_loading_spinners, loading_spinner, LoadingSpinner},
+        GameState,
+    },
 };
-use bevy::prelude::{DespawnRecursiveExt, State};
+use bevy::prelude::{DespawnRecursiveExt, EventReader, State};
+use bevy::text::Text;
 use bevy::{
     prelude::{
         BuildChildren, Button, ButtonBundle, Changed, Color, Commands, Component, Entity,
@@ -20,8 +29,8 @@ use bevy::{
     },
     text::TextStyle,
     ui::{
-        AlignItems, BackgroundColor, FlexDirection, Interaction, JustifyContent, PositionType,
-        Size, Style, UiRect, Val,
+        AlignItems, BackgroundColor, Display, FlexDirection, Interaction, JustifyContent,
+        PositionType, Size, Style, UiRect, Val,
     },
     window::Windows,
 };
@@ -36,8 +45,49 @@ struct GoBackButton;
 struct SelectLevelButton {
     id: i32,
     fen: String,
+    pack_level: Option<String>,
+    par: Option<u32>,
+}
+
+/// A level shown in the selector, from a level pack or from the database.
+#[derive(Debug)]
+struct SelectorLevel {
+    title: String,
+    description: String,
+    tags: Vec<String>,
+    fen: String,
+    number_of_steps: Option<i32>,
+    /// Levels unlock one after another inside their group, which is the pack
+    /// they come from or `None` for database levels.
+    group: Option<String>,
+    level_id: i32,
+    pack_level: Option<String>,
+    par: Option<u32>,
+}
+
+impl SelectorLevel {
+    fn from_database(level: AllLevelsWithSolutions, language: &LanguageResource) -> SelectorLevel {
+        SelectorLevel {
+            title: format!(
+                "{} {}",
+                language.level_selector.level_label.clone(),
+                level.level_id
+            ),
+            description: level.level_description,
+            tags: Vec::new(),
+            fen: level.fen,
+            number_of_steps: level.number_of_steps,
+            group: None,
+            level_id: level.level_id,
+            pack_level: None,
+            par: None,
+        }
+    }
 }
 
+#[derive(Debug, Component)]
+struct LevelErrorText;
+
 #[derive(Debug, Component, PartialEq, Eq)]
 enum PageSwitchButton {
     Forward,
@@ -50,7 +100,7 @@ struct LevelSelectorData {
     pub panels_in_row: f32,
     pub init_left_shift: f32,
     pub space_between_rows: f32,
-    pub all_levels: Vec<AllLevelsWithSolutions>,
+    pub all_levels: Vec<SelectorLevel>,
     pub panels: Vec<Entity>,
 }
 
@@ -67,6 +117,9 @@ impl Plugin for LevelSelectorPlugin {
                     .with_system(despawn_screen::<LevelSelectorView>),
             )
             .init_resource::<LevelSelectorData>()
+            .add_system_set(
+                SystemSet::on_update(GameState::LevelSelector).with_system(add_database_levels),
+            )
             .add_system(level_selector_buttons)
             .add_system(switch_page)
             .add_system(back_to_main_menu);
@@ -78,9 +131,10 @@ fn init_view(
     image_handler: Res<ImageMap>,
     windows: Res<Windows>,
     mut level_selector_data: ResMut<LevelSelectorData>,
-    db_conn: ResMut<DatabaseConnection>,
+    storage: Option<Res<Storage>>,
     config: Res<ConfigResource>,
     language: Res<LanguageResource>,
+    pack_progress: Res<PackProgress>,
 ) {
     let window = windows.get_primary().unwrap();
     let num_of_panels_in_row = (window.width() / (SINGLE_PANEL_WIDTH + 50.0)).floor();
@@ -94,14 +148,48 @@ fn init_view(
     level_selector_data.panels_in_row = num_of_panels_in_row;
     level_selector_data.init_left_shift = init_left_shift;
     level_selector_data.space_between_rows = space_between_rows;
-    level_selector_data.all_levels = get_all_levels_for_player(
-        db_conn,
-        config
-            .local_players
-            .get(config.selected_player_id as usize)
-            .unwrap()
-            .id,
-    );
+    // The main menu opens the selector only once a player exists
+    let player_id = config.selected_player().map(|player| player.id);
+    let language_name = &config.languages[config.selected_language as usize];
+    // Packs from disk come first, levels from the database follow when it is available
+    let mut all_levels = Vec::new();
+    for pack in load_level_packs() {
+        for level in pack.levels {
+            all_levels.push(SelectorLevel {
+                title: level.name.clone(),
+                description: level.description(language_name).to_string(),
+                tags: level.tags.clone(),
+                number_of_steps: player_id.and_then(|id| pack_progress.best_steps(id, &level.id)),
+                group: Some(pack.name.clone()),
+                level_id: 0,
+                pack_level: Some(level.id),
+                par: level.par,
+                fen: level.fen,
+            });
+        }
+    }
+    level_selector_data.all_levels = all_levels;
+    if let (Some(storage), Some(player_id)) = (storage, player_id) {
+        commands.spawn((
+            load_levels_for_player(&storage, player_id),
+            LevelSelectorView,
+        ));
+        commands
+            .spawn(loading_spinner(
+                &image_handler,
+                &language,
+                Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        right: Val::Px(20.0),
+                        top: Val::Px(25.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ))
+            .insert(LevelSelectorView);
+    }
 
     commands
         .spawn(ButtonBundle {
@@ -137,6 +225,30 @@ fn init_view(
         })
         .insert(GoBackButton);
 
+    commands
+        .spawn(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: image_handler.2.get(0).unwrap().clone(),
+                    font_size: 30.0,
+                    color: Color::RED,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(140.0),
+                    top: Val::Px(25.0),
+                    ..Default::default()
+                },
+                display: Display::None,
+                ..Default::default()
+            }),
+        )
+        .insert(LevelSelectorView)
+        .insert(LevelErrorText);
+
     // Level panels
     create_levele_panels(
         commands.borrow_mut(),
@@ -199,6 +311,35 @@ fn init_view(
         });
 }
 
+fn add_database_levels(
+    mut results: EventReader<DbResult<PlayerLevels>>,
+    mut commands: Commands,
+    mut level_selector_data: ResMut<LevelSelectorData>,
+    image_handler: Res<ImageMap>,
+    language: Res<LanguageResource>,
+    spinners: Query<Entity, With<LoadingSpinner>>,
+) {
+    for DbResult(PlayerLevels(levels)) in results.iter() {
+        level_selector_data
+            .all_levels
+            .retain(|level| level.pack_level.is_some());
+        for level in levels {
+            let level = SelectorLevel::from_database(level.clone(), &language);
+            level_selector_data.all_levels.push(level);
+        }
+        for panel in level_selector_data.panels.drain(..) {
+            commands.entity(panel).despawn_recursive();
+        }
+        create_levele_panels(
+            commands.borrow_mut(),
+            level_selector_data.borrow_mut(),
+            image_handler.borrow(),
+            language.borrow(),
+        );
+        despawn_loading_spinners(&mut commands, &spinners);
+    }
+}
+
 fn create_levele_panels(
     commands: &mut Commands,
     level_selector_data: &mut ResMut<LevelSelectorData>,
@@ -256,11 +397,7 @@ fn create_levele_panels(
             let level_label = commands
                 .spawn(
                     TextBundle::from_section(
-                        format!(
-                            "{} {}",
-                            language.level_selector.level_label.clone(),
-                            level_info.level_id
-                        ),
+                        level_info.title.clone(),
                         TextStyle {
                             font: image_handler.2.get(1).unwrap().clone(),
                             font_size: 40.0,
@@ -279,7 +416,7 @@ fn create_levele_panels(
             let level_description = commands
                 .spawn(
                     TextBundle::from_section(
-                        level_info.level_description.to_string(),
+                        level_info.description.clone(),
                         TextStyle {
                             font: image_handler.2.get(0).unwrap().clone(),
                             font_size: 20.0,
@@ -295,6 +432,21 @@ fn create_levele_panels(
                     }),
                 )
                 .id();
+            let tags = commands
+                .spawn(TextBundle::from_section(
+                    level_info
+                        .tags
+                        .iter()
+                        .map(|tag| format!("#{tag}"))
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                    TextStyle {
+                        font: image_handler.2.get(0).unwrap().clone(),
+                        font_size: 16.0,
+                        color: Color::DARK_GRAY,
+                    },
+                ))
+                .id();
             let score = if level_info.number_of_steps.is_some() {
                 format!(
                     "{} {} {}",
@@ -362,16 +514,15 @@ fn create_levele_panels(
             let prev_index_exists_and_solved = if item_counter == 0 {
                 true
             } else {
-                level_selector_data
+                match level_selector_data
                     .all_levels
                     .get(item_counter as usize - 1)
-                    .is_some()
-                    && level_selector_data
-                        .all_levels
-                        .get(item_counter as usize - 1)
-                        .unwrap()
-                        .number_of_steps
-                        .is_some()
+                {
+                    Some(prev_level) => {
+                        prev_level.group != level_info.group || prev_level.number_of_steps.is_some()
+                    }
+                    None => false,
+                }
             };
             if level_info.number_of_steps.is_some()
                 || level_info.number_of_steps.is_none() && prev_index_exists_and_solved
@@ -379,11 +530,14 @@ fn create_levele_panels(
                 commands.entity(select_button).insert(SelectLevelButton {
                     id: level_info.level_id,
                     fen: level_info.fen.clone(),
+                    pack_level: level_info.pack_level.clone(),
+                    par: level_info.par,
                 });
             }
             commands.entity(panel).push_children(&[
                 level_label,
                 level_description,
+                tags,
                 score_label,
                 select_button,
             ]);
@@ -404,15 +558,30 @@ fn level_selector_buttons(
     mut game: ResMut<Game>,
     mut script_res: ResMut<ScriptRes>,
     mut game_state: ResMut<State<GameState>>,
+    mut error_text: Query<(&mut Text, &mut Style), With<LevelErrorText>>,
+    language: Res<LanguageResource>,
 ) {
     for (interaction, level_info, mut back_color) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 *back_color = BackgroundColor(Color::YELLOW);
-                *game =
-                    Game::init_from_fen(level_info.fen.clone(), level_info.id, GameMode::Tutorial);
-                *script_res = ScriptRes::new();
-                game_state.set(GameState::Game).unwrap();
+                match Game::init_from_fen(level_info.fen.clone(), level_info.id, GameMode::Tutorial)
+                {
+                    Ok(mut new_game) => {
+                        new_game.pack_level = level_info.pack_level.clone();
+                        new_game.par = level_info.par;
+                        *game = new_game;
+                        *script_res = ScriptRes::new();
+                        game_state.set(GameState::Game).unwrap();
+                    }
+                    Err(err) => {
+                        for (mut text, mut style) in &mut error_text {
+                            text.sections[0].value =
+                                format!("{}: {err}", language.game.level_error_label);
+                            style.display = Display::Flex;
+                        }
+                    }
+                }
             }
             Interaction::Hovered => {
                 *back_color = BackgroundColor(Color::AQUAMARINE);
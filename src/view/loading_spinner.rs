@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+
+use crate::utilities::{database_plugin::DbError, language_plugin::LanguageResource};
+
+use super::image_handler::ImageMap;
+
+const SECONDS_PER_DOT: f32 = 0.3;
+const MAX_DOTS: usize = 3;
+
+/// Shown while a view waits for a `DbTask`. The view spawns it with the task
+/// and despawns it when the result arrives. When a call fails the spinner stops
+/// and says so, as no result is coming.
+#[derive(Debug, Component)]
+pub struct LoadingSpinner {
+    failed: bool,
+}
+
+pub struct LoadingSpinnerPlugin;
+
+impl Plugin for LoadingSpinnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spin_loading_spinners)
+            .add_system(stop_loading_spinners);
+    }
+}
+
+pub fn loading_spinner(
+    image_handler: &ImageMap,
+    language: &LanguageResource,
+    style: Style,
+) -> (TextBundle, LoadingSpinner) {
+    let text_style = TextStyle {
+        font: image_handler.2.get(0).unwrap().clone(),
+        font_size: 30.0,
+        color: Color::DARK_GRAY,
+    };
+    (
+        TextBundle::from_sections([
+            TextSection::new(language.loading_label.clone(), text_style.clone()),
+            TextSection::new("", text_style),
+        ])
+        .with_style(style),
+        LoadingSpinner { failed: false },
+    )
+}
+
+pub fn despawn_loading_spinners(
+    commands: &mut Commands,
+    spinners: &Query<Entity, With<LoadingSpinner>>,
+) {
+    for spinner in spinners {
+        commands.entity(spinner).despawn_recursive();
+    }
+}
+
+fn spin_loading_spinners(time: Res<Time>, mut spinners: Query<(&mut Text, &LoadingSpinner)>) {
+    let dots = (time.elapsed_seconds() / SECONDS_PER_DOT) as usize % (MAX_DOTS + 1);
+    for (mut text, spinner) in &mut spinners {
+        if !spinner.failed {
+            text.sections[1].value = ".".repeat(dots);
+        }
+    }
+}
+
+fn stop_loading_spinners(
+    mut errors: EventReader<DbError>,
+    mut spinners: Query<(&mut Text, &mut LoadingSpinner)>,
+    language: Res<LanguageResource>,
+) {
+    if errors.iter().count() == 0 {
+        return;
+    }
+    for (mut text, mut spinner) in &mut spinners {
+        spinner.failed = true;
+        text.sections[0].value = language.database_error_label.clone();
+        text.sections[1].value.clear();
+    }
+}
@@ -5,31 +5,45 @@ use std::borrow::BorrowMut;
 
 use bevy::{
     app::AppExit,
+    input::Input,
     prelude::{
-        BuildChildren, Button, ButtonBundle, Changed, Color, Commands, Component, EventWriter,
-        NodeBundle, Plugin, Query, Res, ResMut, State, SystemSet, TextBundle, With,
+        BuildChildren, Button, ButtonBundle, Changed, Color, Commands, Component, Entity,
+        EventReader, EventWriter, KeyCode, NodeBundle, Plugin, Query, Res, ResMut, State,
+        SystemSet, TextBundle, With, Without,
     },
-    text::{Text, TextStyle},
+    text::{Text, TextSection, TextStyle},
     ui::{
         AlignItems, BackgroundColor, Display, FlexDirection, Interaction, JustifyContent,
         PositionType, Size, Style, UiRect, Val,
     },
+    window::ReceivedCharacter,
 };
 
 use crate::{
-    model::game_model::game::{Game, GameMode},
+    model::game_model::{
+        fen::FenError,
+        game::{Game, GameMode},
+        generator::{challenge_code, parse_challenge_code, BoardSeed, Difficulty},
+    },
     utilities::{
         database_plugin::{
-            create_new_player, get_random_challenge_fen, update_cofig_file, ConfigResource,
-            DatabaseConnection,
+            create_new_player, load_challenge_for_seed, load_random_challenge, update_cofig_file,
+            ChallengeBoard, ConfigResource, DatabaseStatus, DbResult, DbTask, NewPlayer,
         },
         language_plugin::LanguageResource,
         script_plugin::ScriptRes,
+        storage::Storage,
+    },
+    view::{
+        despawn_screen,
+        image_handler::ImageMap,
+        loading_spinner::{despawn_loading_spinners, loading_spinner, LoadingSpinner},
+        GameState,
     },
-    view::{despawn_screen, image_handler::ImageMap, GameState},
 };
 
 const BUTTON_MARGIN: f32 = 20.0;
+const MAX_CHALLENGE_CODE_LENGTH: usize = 18;
 
 #[derive(Debug, Component)]
 struct PlayerDisplayText;
@@ -38,8 +52,11 @@ struct PlayerDisplayText;
 enum MenuButtonAction {
     Tutorial,
     Challenge,
+    ChallengeDifficulty(Difficulty),
+    ChallengeCode,
     Multiplayer,
     Scoreboard,
+    LevelEditor,
     LanguageBack,
     LanguageForward,
     PlayerBack,
@@ -48,12 +65,52 @@ enum MenuButtonAction {
     Quit,
 }
 
+impl MenuButtonAction {
+    /// Actions that read from the database, so they are refused while offline.
+    fn needs_database(&self) -> bool {
+        matches!(
+            self,
+            MenuButtonAction::ChallengeDifficulty(_)
+                | MenuButtonAction::ChallengeCode
+                | MenuButtonAction::Multiplayer
+                | MenuButtonAction::Scoreboard
+        )
+    }
+
+    /// Actions that save or show results of the selected player, so they wait
+    /// until the first player is created.
+    fn needs_player(&self) -> bool {
+        matches!(
+            self,
+            MenuButtonAction::Tutorial
+                | MenuButtonAction::ChallengeDifficulty(_)
+                | MenuButtonAction::ChallengeCode
+                | MenuButtonAction::Multiplayer
+                | MenuButtonAction::Scoreboard
+        )
+    }
+}
+
 #[derive(Debug, Component)]
 struct ReloadText;
 
+#[derive(Debug, Component)]
+struct ChallengeCodeText;
+
+#[derive(Debug, Component)]
+struct DifficultyPanel;
+
+#[derive(Debug, Component)]
+struct LevelErrorText;
+
 #[derive(Debug, Component)]
 struct MainMenuView;
 
+/// Shown while the game is offline, the database can go away or come back
+/// while the menu is open.
+#[derive(Debug, Component)]
+struct OfflineBanner;
+
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
@@ -62,7 +119,14 @@ impl Plugin for MainMenuPlugin {
             .add_system_set(
                 SystemSet::on_exit(GameState::MainMenu).with_system(despawn_screen::<MainMenuView>),
             )
-            .add_system(menu_actions);
+            .add_system_set(
+                SystemSet::on_update(GameState::MainMenu)
+                    .with_system(start_challenge)
+                    .with_system(update_player_text)
+                    .with_system(update_offline_banner),
+            )
+            .add_system(menu_actions)
+            .add_system(type_challenge_code);
     }
 }
 
@@ -71,8 +135,14 @@ fn init_setup(
     image_handler: Res<ImageMap>,
     mut config: ResMut<ConfigResource>,
     language: Res<LanguageResource>,
-    mut db_conn: ResMut<DatabaseConnection>,
+    storage: Option<Res<Storage>>,
+    db_status: Res<DatabaseStatus>,
+    pending_players: Query<(), With<DbTask<NewPlayer>>>,
 ) {
+    if config.local_players.is_empty() && pending_players.is_empty() {
+        create_new_player(&mut commands, storage.as_deref(), &mut config);
+    }
+
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -95,6 +165,37 @@ fn init_setup(
         })
         .insert(MainMenuView)
         .with_children(|parent| {
+            let (reason, display) = match &*db_status {
+                DatabaseStatus::Online => (String::new(), Display::None),
+                DatabaseStatus::Offline(reason) => (format!(" ({reason})"), Display::Flex),
+            };
+            parent.spawn((
+                TextBundle::from_sections([
+                    TextSection::new(
+                        language.main_menu.offline_banner.clone(),
+                        TextStyle {
+                            font: image_handler.2.get(0).unwrap().clone(),
+                            font_size: 30.0,
+                            color: Color::RED,
+                        },
+                    ),
+                    TextSection::new(
+                        reason,
+                        TextStyle {
+                            font: image_handler.2.get(0).unwrap().clone(),
+                            font_size: 20.0,
+                            color: Color::RED,
+                        },
+                    ),
+                ])
+                .with_style(Style {
+                    display,
+                    margin: UiRect::top(Val::Px(10.0)),
+                    ..Default::default()
+                }),
+                OfflineBanner,
+            ));
+
             parent.spawn(
                 TextBundle::from_section(
                     "Game Title",
@@ -170,6 +271,113 @@ fn init_setup(
                 })
                 .insert(MenuButtonAction::Challenge);
 
+            // Challenge difficulty panel, shown by the challenge button
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Px(330.0),
+                            height: Val::Px(60.0),
+                        },
+                        display: Display::None,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::vertical(Val::Px(BUTTON_MARGIN)),
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::WHITE),
+                    ..Default::default()
+                })
+                .with_children(|node| {
+                    for (i, difficulty) in Difficulty::ALL.into_iter().enumerate() {
+                        node.spawn(ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(100.0), Val::Px(50.0)),
+                                margin: UiRect::all(Val::Px(5.0)),
+                                align_items: AlignItems::Center,
+                                justify_content: JustifyContent::Center,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        })
+                        .with_children(|button| {
+                            button.spawn(TextBundle::from_section(
+                                language.main_menu.difficulty_buttons[i].clone(),
+                                TextStyle {
+                                    font: image_handler.2.get(0).unwrap().clone(),
+                                    font_size: 25.0,
+                                    color: Color::BLACK,
+                                },
+                            ));
+                        })
+                        .insert(MenuButtonAction::ChallengeDifficulty(difficulty));
+                    }
+                })
+                .insert(DifficultyPanel);
+
+            // Challenge code panel
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Px(330.0),
+                            height: Val::Px(60.0),
+                        },
+                        align_items: AlignItems::Center,
+                        margin: UiRect::vertical(Val::Px(BUTTON_MARGIN)),
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..Default::default()
+                    },
+                    background_color: BackgroundColor(Color::WHITE),
+                    ..Default::default()
+                })
+                .with_children(|node| {
+                    node.spawn(
+                        TextBundle::from_sections([
+                            TextSection::new(
+                                format!("{} ", language.main_menu.challenge_code_label),
+                                TextStyle {
+                                    font: image_handler.2.get(0).unwrap().clone(),
+                                    font_size: 25.0,
+                                    color: Color::BLACK,
+                                },
+                            ),
+                            TextSection::from_style(TextStyle {
+                                font: image_handler.2.get(0).unwrap().clone(),
+                                font_size: 25.0,
+                                color: Color::BLACK,
+                            }),
+                        ])
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(5.0)),
+                            ..Default::default()
+                        }),
+                    )
+                    .insert(ChallengeCodeText);
+                    node.spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(100.0), Val::Px(50.0)),
+                            margin: UiRect::all(Val::Px(5.0)),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..Default::default()
+                        },
+                        background_color: BackgroundColor(Color::GRAY),
+                        ..Default::default()
+                    })
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            language.main_menu.challenge_code_button.clone(),
+                            TextStyle {
+                                font: image_handler.2.get(0).unwrap().clone(),
+                                font_size: 25.0,
+                                color: Color::BLACK,
+                            },
+                        ));
+                    })
+                    .insert(MenuButtonAction::ChallengeCode);
+                });
+
             // Multiplayer mode Button
             parent
                 .spawn(ButtonBundle {
@@ -226,6 +434,34 @@ fn init_setup(
                 })
                 .insert(MenuButtonAction::Scoreboard);
 
+            // Level editor button
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size {
+                            width: Val::Px(200.0),
+                            height: Val::Px(50.0),
+                        },
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::vertical(Val::Px(BUTTON_MARGIN)),
+                        justify_content: JustifyContent::Center,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        language.main_menu.level_editor_button.clone(),
+                        TextStyle {
+                            font: image_handler.2.get(0).unwrap().clone(),
+                            font_size: 40.0,
+                            color: Color::BLACK,
+                        },
+                    ));
+                })
+                .insert(MenuButtonAction::LevelEditor);
+
             // Change language panel
             parent
                 .spawn(NodeBundle {
@@ -287,10 +523,6 @@ fn init_setup(
                     .insert(MenuButtonAction::LanguageForward);
                 });
 
-            if config.local_players.is_empty() {
-                create_new_player(&mut db_conn, &mut config);
-            }
-
             // Change player panel
             parent
                 .spawn(NodeBundle {
@@ -320,15 +552,7 @@ fn init_setup(
                     .insert(MenuButtonAction::PlayerBack);
                     node.spawn(
                         TextBundle::from_section(
-                            format!(
-                                "{} {}",
-                                language.main_menu.player_panel.clone(),
-                                config
-                                    .local_players
-                                    .get(config.selected_player_id as usize)
-                                    .unwrap()
-                                    .name
-                            ),
+                            player_text(&config, &language),
                             TextStyle {
                                 font: image_handler.2.get(0).unwrap().clone(),
                                 font_size: 25.0,
@@ -425,6 +649,23 @@ fn init_setup(
                     }),
                 )
                 .insert(ReloadText);
+
+            parent
+                .spawn(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: image_handler.2.get(0).unwrap().clone(),
+                            font_size: 30.0,
+                            color: Color::RED,
+                        },
+                    )
+                    .with_style(Style {
+                        display: Display::None,
+                        ..Default::default()
+                    }),
+                )
+                .insert(LevelErrorText);
         });
 }
 
@@ -433,28 +674,82 @@ fn menu_actions(
         (&Interaction, &MenuButtonAction, &mut BackgroundColor),
         (Changed<Interaction>, With<Button>),
     >,
-    mut player_display_text: Query<&mut Text, With<PlayerDisplayText>>,
-    mut reload_text: Query<&mut Style, With<ReloadText>>,
+    challenge_code_text: Query<&Text, (With<ChallengeCodeText>, Without<LevelErrorText>)>,
+    mut reload_text: Query<&mut Style, (With<ReloadText>, Without<LevelErrorText>)>,
+    mut difficulty_panel: Query<
+        &mut Style,
+        (
+            With<DifficultyPanel>,
+            Without<ReloadText>,
+            Without<LevelErrorText>,
+        ),
+    >,
+    mut error_text: Query<(&mut Text, &mut Style), (With<LevelErrorText>, Without<ReloadText>)>,
+    pending_challenges: Query<(), With<DbTask<Option<ChallengeBoard>>>>,
     mut app_exit_events: EventWriter<AppExit>,
+    mut commands: Commands,
     mut game_state: ResMut<State<GameState>>,
-    mut db_conn: ResMut<DatabaseConnection>,
-    mut game: ResMut<Game>,
-    mut script_res: ResMut<ScriptRes>,
+    storage: Option<Res<Storage>>,
     mut config: ResMut<ConfigResource>,
+    image_handler: Res<ImageMap>,
+    language: Res<LanguageResource>,
 ) {
     for (interaction, button_action, mut back_color) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 *back_color = BackgroundColor(Color::YELLOW);
+                if storage.is_none() && button_action.needs_database() {
+                    for (mut text, mut style) in &mut error_text {
+                        text.sections[0].value = language.main_menu.offline_label.clone();
+                        style.display = Display::Flex;
+                    }
+                    continue;
+                }
+                if config.selected_player().is_none() && button_action.needs_player() {
+                    for (mut text, mut style) in &mut error_text {
+                        text.sections[0].value = language.main_menu.no_player_label.clone();
+                        style.display = Display::Flex;
+                    }
+                    continue;
+                }
                 match *button_action {
                     MenuButtonAction::Tutorial => {
                         game_state.set(GameState::LevelSelector).unwrap();
                     }
                     MenuButtonAction::Challenge => {
-                        let (prefab_id, fen) = get_random_challenge_fen(db_conn.borrow_mut());
-                        *game = Game::init_from_fen(fen, prefab_id, GameMode::Challenge);
-                        *script_res = ScriptRes::new();
-                        game_state.set(GameState::Game).unwrap();
+                        for mut style in &mut difficulty_panel {
+                            style.display = match style.display {
+                                Display::None => Display::Flex,
+                                Display::Flex => Display::None,
+                            };
+                        }
+                    }
+                    MenuButtonAction::ChallengeDifficulty(_) | MenuButtonAction::ChallengeCode
+                        if !pending_challenges.is_empty() =>
+                    {
+                        // A board is already on its way
+                    }
+                    MenuButtonAction::ChallengeDifficulty(difficulty) => {
+                        let storage = storage.as_ref().unwrap();
+                        commands.spawn((load_random_challenge(storage, difficulty), MainMenuView));
+                        spawn_challenge_spinner(&mut commands, &image_handler, &language);
+                    }
+                    MenuButtonAction::ChallengeCode => {
+                        let code = match challenge_code_text.get_single() {
+                            Ok(text) => text.sections[1].value.clone(),
+                            Err(_) => continue,
+                        };
+                        match parse_challenge_code(&code) {
+                            Some((prefab_id, board_seed)) => {
+                                let storage = storage.as_ref().unwrap();
+                                commands.spawn((
+                                    load_challenge_for_seed(storage, prefab_id, board_seed),
+                                    MainMenuView,
+                                ));
+                                spawn_challenge_spinner(&mut commands, &image_handler, &language);
+                            }
+                            None => show_challenge_code_error(&mut error_text, &language, &code),
+                        }
                     }
                     MenuButtonAction::Multiplayer => {
                         game_state.set(GameState::Multiplayer).unwrap();
@@ -462,6 +757,9 @@ fn menu_actions(
                     MenuButtonAction::Scoreboard => {
                         game_state.set(GameState::Scoreboard).unwrap();
                     }
+                    MenuButtonAction::LevelEditor => {
+                        game_state.set(GameState::LevelEditor).unwrap();
+                    }
                     MenuButtonAction::LanguageBack => {
                         let num_of_langs = config.languages.len() as i32;
                         let new_selected_ind = (config.selected_language - 1) % num_of_langs;
@@ -480,49 +778,22 @@ fn menu_actions(
                             style.display = Display::Flex;
                         }
                     }
-                    MenuButtonAction::PlayerBack => {
-                        for mut text in &mut player_display_text {
-                            config.selected_player_id =
-                                (config.selected_player_id - 1) % config.local_players.len() as i32;
-                            text.sections[0].value = format!(
-                                "Player: {}",
-                                config
-                                    .local_players
-                                    .get(config.selected_player_id as usize)
-                                    .unwrap()
-                                    .name
-                            );
-                        }
+                    // `update_player_text` shows the newly selected player
+                    MenuButtonAction::PlayerBack if !config.local_players.is_empty() => {
+                        config.selected_player_id = (config.selected_player_id - 1)
+                            .rem_euclid(config.local_players.len() as i32);
                     }
-                    MenuButtonAction::PlayerForward => {
-                        for mut text in &mut player_display_text {
-                            config.selected_player_id =
-                                (config.selected_player_id + 1) % config.local_players.len() as i32;
-                            text.sections[0].value = format!(
-                                "Player: {}",
-                                config
-                                    .local_players
-                                    .get(config.selected_player_id as usize)
-                                    .unwrap()
-                                    .name
-                            );
-                        }
+                    MenuButtonAction::PlayerForward if !config.local_players.is_empty() => {
+                        config.selected_player_id = (config.selected_player_id + 1)
+                            .rem_euclid(config.local_players.len() as i32);
                     }
+                    MenuButtonAction::PlayerBack | MenuButtonAction::PlayerForward => {}
                     MenuButtonAction::Quit => {
                         app_exit_events.send(AppExit);
                     }
                     MenuButtonAction::CreatePlayer => {
-                        create_new_player(db_conn.borrow_mut(), config.borrow_mut());
-                        for mut text in &mut player_display_text {
-                            text.sections[0].value = format!(
-                                "Player: {}",
-                                config
-                                    .local_players
-                                    .get(config.selected_player_id as usize)
-                                    .unwrap()
-                                    .name
-                            );
-                        }
+                        // The player text follows the config once the player is added
+                        create_new_player(&mut commands, storage.as_deref(), config.borrow_mut());
                     }
                 }
             }
@@ -535,3 +806,170 @@ fn menu_actions(
         }
     }
 }
+
+fn spawn_challenge_spinner(
+    commands: &mut Commands,
+    image_handler: &ImageMap,
+    language: &LanguageResource,
+) {
+    commands
+        .spawn(loading_spinner(
+            image_handler,
+            language,
+            Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Percent(8.0),
+                    left: Val::Percent(45.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ))
+        .insert(MainMenuView);
+}
+
+fn show_challenge_code_error(
+    error_text: &mut Query<
+        (&mut Text, &mut Style),
+        (
+            With<LevelErrorText>,
+            Without<PlayerDisplayText>,
+            Without<ReloadText>,
+        ),
+    >,
+    language: &LanguageResource,
+    code: &str,
+) {
+    for (mut text, mut style) in error_text {
+        text.sections[0].value = format!("{}: \"{code}\"", language.main_menu.challenge_code_error);
+        style.display = Display::Flex;
+    }
+}
+
+/// Starts the challenge once its board came from the database.
+fn start_challenge(
+    mut commands: Commands,
+    mut boards: EventReader<DbResult<Option<ChallengeBoard>>>,
+    mut error_text: Query<
+        (&mut Text, &mut Style),
+        (
+            With<LevelErrorText>,
+            Without<PlayerDisplayText>,
+            Without<ReloadText>,
+        ),
+    >,
+    spinners: Query<Entity, With<LoadingSpinner>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut game: ResMut<Game>,
+    mut script_res: ResMut<ScriptRes>,
+    language: Res<LanguageResource>,
+) {
+    for DbResult(board) in boards.iter() {
+        despawn_loading_spinners(&mut commands, &spinners);
+        let board = match board {
+            Some(board) => board,
+            None => {
+                for (mut text, mut style) in &mut error_text {
+                    text.sections[0].value = language.no_challenges_label.clone();
+                    style.display = Display::Flex;
+                }
+                continue;
+            }
+        };
+        let (fen, par) = match &board.board {
+            Some(board) => board.clone(),
+            None => {
+                let code = challenge_code(board.prefab_id, board.board_seed);
+                show_challenge_code_error(&mut error_text, &language, &code);
+                continue;
+            }
+        };
+        match init_challenge(fen, par, board.prefab_id, board.board_seed) {
+            Ok(new_game) => {
+                *game = new_game;
+                *script_res = ScriptRes::new();
+                game_state.set(GameState::Game).unwrap();
+            }
+            Err(err) => {
+                for (mut text, mut style) in &mut error_text {
+                    text.sections[0].value = format!("{}: {err}", language.game.level_error_label);
+                    style.display = Display::Flex;
+                }
+            }
+        }
+    }
+}
+
+fn player_text(config: &ConfigResource, language: &LanguageResource) -> String {
+    // Empty until the first player comes back from the database
+    let name = config
+        .selected_player()
+        .map(|player| player.name.as_str())
+        .unwrap_or_default();
+    format!("{} {}", language.main_menu.player_panel, name)
+}
+
+fn update_offline_banner(
+    db_status: Res<DatabaseStatus>,
+    mut banners: Query<(&mut Text, &mut Style), With<OfflineBanner>>,
+) {
+    if !db_status.is_changed() {
+        return;
+    }
+    for (mut text, mut style) in &mut banners {
+        match &*db_status {
+            DatabaseStatus::Online => style.display = Display::None,
+            DatabaseStatus::Offline(reason) => {
+                text.sections[1].value = format!(" ({reason})");
+                style.display = Display::Flex;
+            }
+        }
+    }
+}
+
+fn update_player_text(
+    config: Res<ConfigResource>,
+    language: Res<LanguageResource>,
+    mut player_display_text: Query<&mut Text, With<PlayerDisplayText>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+    for mut text in &mut player_display_text {
+        text.sections[0].value = player_text(&config, &language);
+    }
+}
+
+fn init_challenge(
+    fen: String,
+    par: u32,
+    prefab_id: i32,
+    board_seed: BoardSeed,
+) -> Result<Game, FenError> {
+    let mut game = Game::init_from_fen(fen, prefab_id, GameMode::Challenge)?;
+    game.seed = Some(board_seed);
+    // The generator already solved the board, so the saved result has its par
+    game.par = Some(par);
+    Ok(game)
+}
+
+fn type_challenge_code(
+    mut received_characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    mut challenge_code_text: Query<&mut Text, With<ChallengeCodeText>>,
+) {
+    for mut text in &mut challenge_code_text {
+        let code = &mut text.sections[1].value;
+        for event in received_characters.iter() {
+            if (event.char.is_ascii_alphanumeric() || event.char == '-')
+                && code.len() < MAX_CHALLENGE_CODE_LENGTH
+            {
+                code.push(event.char.to_ascii_uppercase());
+            }
+        }
+        if keys.just_pressed(KeyCode::Back) {
+            code.pop();
+        }
+    }
+}
@@ -2,7 +2,9 @@ use bevy::prelude::*;
 
 pub mod game_view;
 pub mod image_handler;
+pub mod level_editor_view;
 pub mod level_selector_view;
+pub mod loading_spinner;
 pub mod main_menu;
 pub mod multiplayer_view;
 pub mod scoreboard_view;
@@ -14,6 +16,7 @@ pub enum GameState {
     MainMenu,
     Scoreboard,
     Multiplayer,
+    LevelEditor,
 }
 
 fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
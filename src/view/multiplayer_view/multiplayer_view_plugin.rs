@@ -1,12 +1,12 @@
 #![allow(clippy::type_complexity)]
-
-use std::borrow::BorrowMut;
+#![allow(clippy::too_many_arguments)]
 
 use bevy::{
     ecs::schedule::ShouldRun,
     prelude::{
-        BuildChildren, Button, ButtonBundle, Changed, Color, Commands, Component, EventWriter,
-        NodeBundle, Plugin, Query, Res, ResMut, State, SystemSet, TextBundle, With, Without,
+        BuildChildren, Button, ButtonBundle, Changed, Color, Commands, Component, Entity,
+        EventReader, EventWriter, NodeBundle, Parent, Plugin, Query, Res, ResMut, State, SystemSet,
+        TextBundle, With, Without,
     },
     text::{Text, TextStyle},
     ui::{
@@ -25,8 +25,8 @@ use crate::{
     model::game_model::game::{Game, GameMode},
     utilities::{
         database_plugin::{
-            get_challenge_fen_at_ind, get_next_challenge_fen, get_prev_challenge_fen,
-            DatabaseConnection,
+            load_challenge_at_ind, load_next_challenge, load_prev_challenge, DbResult, DbTask,
+            PrefabChoice,
         },
         language_plugin::LanguageResource,
         network_plugin::{
@@ -34,8 +34,14 @@ use crate::{
             SendLevelDataToClient, SendStartSignalToClient,
         },
         script_plugin::ScriptRes,
+        storage::Storage,
+    },
+    view::{
+        despawn_screen,
+        image_handler::ImageMap,
+        loading_spinner::{despawn_loading_spinners, loading_spinner, LoadingSpinner},
+        GameState,
     },
-    view::{despawn_screen, image_handler::ImageMap, GameState},
 };
 
 use local_ip_address::local_ip;
@@ -87,6 +93,9 @@ enum SwitchLevel {
 #[derive(Debug, Component)]
 struct StartLevel;
 
+#[derive(Debug, Component)]
+struct LevelErrorText;
+
 pub struct MultiplayerViewPlugin;
 
 impl Plugin for MultiplayerViewPlugin {
@@ -99,8 +108,10 @@ impl Plugin for MultiplayerViewPlugin {
             .add_system(back_to_main_menu)
             .add_system(choose_network_option)
             .add_system(choose_level)
+            .add_system(select_level)
             .add_system(start_game)
             .add_system(update_connection_status_view)
+            .add_system(update_level_error_view)
             .add_system_set(
                 SystemSet::new()
                     .with_run_criteria(cond_to_connect_to_client)
@@ -640,6 +651,23 @@ fn init_view(
                 })
                 .insert(LevelPanel);
 
+            parent
+                .spawn(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: image_handler.2.get(0).unwrap().clone(),
+                            font_size: 30.0,
+                            color: Color::RED,
+                        },
+                    )
+                    .with_style(Style {
+                        display: Display::None,
+                        ..Default::default()
+                    }),
+                )
+                .insert(LevelErrorText);
+
             // Scores label
             parent.spawn(
                 TextBundle::from_section(
@@ -1000,8 +1028,10 @@ fn connect_to_client(
     >,
     mut network_res: ResMut<NetworkResource>,
     mut level_selection_panel: Query<&mut Style, With<LevelPanel>>,
-    mut db_conn: ResMut<DatabaseConnection>,
-    mut event_sender: EventWriter<SendLevelDataToClient>,
+    level_name_row: Query<&Parent, With<LevelNameText>>,
+    mut commands: Commands,
+    storage: Option<Res<Storage>>,
+    image_handler: Res<ImageMap>,
     language: Res<LanguageResource>,
 ) {
     let endpoint = server.endpoint_mut();
@@ -1020,13 +1050,10 @@ fn connect_to_client(
             for mut style in &mut level_selection_panel {
                 style.display = Display::Flex;
             }
-            let (id, fen, level_name) = get_challenge_fen_at_ind(db_conn.borrow_mut(), 0);
-            network_res.level_selection_data.selected_level_id = 0;
-            network_res.level_selection_data.fen = fen;
-            network_res.level_selection_data.level_id = id;
-            network_res.level_selection_data.level_name = level_name;
-            let event = SendLevelDataToClient::default();
-            event_sender.send(event);
+            if let Some(storage) = storage.as_ref() {
+                commands.spawn((load_challenge_at_ind(storage, 0), MultiplayerView));
+                spawn_level_spinner(&mut commands, &level_name_row, &image_handler, &language);
+            }
         }
         2.. => {
             let saved_client =
@@ -1084,43 +1111,34 @@ fn choose_level(
         (&Interaction, &SwitchLevel, &mut BackgroundColor),
         (Changed<Interaction>, With<Button>, With<SwitchLevel>),
     >,
-    mut network_res: ResMut<NetworkResource>,
-    mut db_conn: ResMut<DatabaseConnection>,
-    mut event_sender: EventWriter<SendLevelDataToClient>,
+    network_res: Res<NetworkResource>,
+    pending_choices: Query<(), With<DbTask<Option<PrefabChoice>>>>,
+    level_name_row: Query<&Parent, With<LevelNameText>>,
+    mut commands: Commands,
+    storage: Option<Res<Storage>>,
+    image_handler: Res<ImageMap>,
+    language: Res<LanguageResource>,
 ) {
+    // Multiplayer can not be opened offline, so the levels are always there
+    let storage = match storage {
+        Some(storage) => storage,
+        None => return,
+    };
     for (interaction, action_type, mut back_color) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 *back_color = BackgroundColor(Color::YELLOW);
-                let new_level_selection: SelectedLevelData = match *action_type {
-                    SwitchLevel::Back => {
-                        let (ind, id, fen, name) = get_prev_challenge_fen(
-                            db_conn.borrow_mut(),
-                            network_res.level_selection_data.selected_level_id,
-                        );
-                        SelectedLevelData {
-                            selected_level_id: ind,
-                            level_id: id,
-                            fen,
-                            level_name: name,
-                        }
-                    }
-                    SwitchLevel::Forward => {
-                        let (ind, id, fen, name) = get_next_challenge_fen(
-                            db_conn.borrow_mut(),
-                            network_res.level_selection_data.selected_level_id,
-                        );
-                        SelectedLevelData {
-                            selected_level_id: ind,
-                            level_id: id,
-                            fen,
-                            level_name: name,
-                        }
-                    }
+                // The level shown is still the one before the last click
+                if !pending_choices.is_empty() {
+                    continue;
+                }
+                let selected_level_id = network_res.level_selection_data.selected_level_id;
+                let task = match *action_type {
+                    SwitchLevel::Back => load_prev_challenge(&storage, selected_level_id),
+                    SwitchLevel::Forward => load_next_challenge(&storage, selected_level_id),
                 };
-                network_res.level_selection_data = new_level_selection;
-                let event = SendLevelDataToClient::default();
-                event_sender.send(event);
+                commands.spawn((task, MultiplayerView));
+                spawn_level_spinner(&mut commands, &level_name_row, &image_handler, &language);
             }
             Interaction::Hovered => {
                 *back_color = BackgroundColor(Color::AQUAMARINE);
@@ -1132,6 +1150,55 @@ fn choose_level(
     }
 }
 
+/// Shows the prefab that came from the database and sends it to the client.
+fn select_level(
+    mut commands: Commands,
+    mut choices: EventReader<DbResult<Option<PrefabChoice>>>,
+    spinners: Query<Entity, With<LoadingSpinner>>,
+    mut network_res: ResMut<NetworkResource>,
+    mut event_sender: EventWriter<SendLevelDataToClient>,
+    language: Res<LanguageResource>,
+) {
+    for DbResult(choice) in choices.iter() {
+        despawn_loading_spinners(&mut commands, &spinners);
+        let choice = match choice {
+            Some(choice) => choice,
+            None => {
+                network_res.level_error = Some(language.no_challenges_label.clone());
+                continue;
+            }
+        };
+        network_res.level_selection_data = SelectedLevelData {
+            selected_level_id: choice.index,
+            level_id: choice.prefab_id,
+            fen: choice.fen.clone(),
+            level_name: choice.level_name.clone(),
+        };
+        let event = SendLevelDataToClient::default();
+        event_sender.send(event);
+    }
+}
+
+fn spawn_level_spinner(
+    commands: &mut Commands,
+    level_name_row: &Query<&Parent, With<LevelNameText>>,
+    image_handler: &ImageMap,
+    language: &LanguageResource,
+) {
+    for row in level_name_row {
+        commands.entity(row.get()).with_children(|node| {
+            node.spawn(loading_spinner(
+                image_handler,
+                language,
+                Style {
+                    margin: UiRect::all(Val::Px(5.0)),
+                    ..Default::default()
+                },
+            ));
+        });
+    }
+}
+
 fn start_game(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
@@ -1147,16 +1214,24 @@ fn start_game(
         match *interaction {
             Interaction::Clicked => {
                 *back_color = BackgroundColor(Color::YELLOW);
-                *game = Game::init_from_fen(
+                match Game::init_from_fen(
                     network_res.level_selection_data.fen.clone(),
                     network_res.level_selection_data.level_id,
                     GameMode::Multiplayer,
-                );
-                *script_res = ScriptRes::new();
-                game_state.set(GameState::Game).unwrap();
-                event_sender.send(SendStartSignalToClient::default());
-                network_res.my_game_score.reset();
-                network_res.opponent_game_score.reset();
+                ) {
+                    Ok(new_game) => {
+                        network_res.level_error = None;
+                        *game = new_game;
+                        *script_res = ScriptRes::new();
+                        game_state.set(GameState::Game).unwrap();
+                        event_sender.send(SendStartSignalToClient::default());
+                        network_res.my_game_score.reset();
+                        network_res.opponent_game_score.reset();
+                    }
+                    Err(err) => {
+                        network_res.level_error = Some(err.to_string());
+                    }
+                }
             }
             Interaction::Hovered => {
                 *back_color = BackgroundColor(Color::AQUAMARINE);
@@ -1254,3 +1329,18 @@ fn update_connection_status_view(
         };
     }
 }
+
+fn update_level_error_view(
+    network_res: Res<NetworkResource>,
+    mut error_text: Query<(&mut Text, &mut Style), With<LevelErrorText>>,
+    language: Res<LanguageResource>,
+) {
+    for (mut text, mut style) in &mut error_text {
+        if let Some(err) = &network_res.level_error {
+            text.sections[0].value = format!("{}: {err}", language.game.level_error_label);
+            style.display = Display::Flex;
+        } else {
+            style.display = Display::None;
+        }
+    }
+}
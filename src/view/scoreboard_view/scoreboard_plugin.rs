@@ -1,13 +1,13 @@
 #![allow(clippy::type_complexity)]
-
-use std::borrow::BorrowMut;
+#![allow(clippy::too_many_arguments)]
 
 use bevy::{
     prelude::{
-        App, BuildChildren, Button, ButtonBundle, Changed, Color, Commands, Component, NodeBundle,
-        Plugin, Query, Res, ResMut, State, SystemSet, TextBundle, With,
+        App, BuildChildren, Button, ButtonBundle, Changed, ChildBuilder, Color, Commands,
+        Component, DespawnRecursiveExt, Entity, EventReader, NodeBundle, Plugin, Query, Res,
+        ResMut, Resource, State, SystemSet, TextBundle, With,
     },
-    text::TextStyle,
+    text::{Text, TextStyle},
     ui::{
         AlignItems, BackgroundColor, FlexDirection, Interaction, JustifyContent, PositionType,
         Size, Style, UiRect, Val,
@@ -17,12 +17,13 @@ use bevy::{
 use crate::{
     utilities::{
         database_plugin::{
-            get_best_ten_challenge_scores_for_player, get_best_ten_multiplayer_scores_for_player,
-            ConfigResource, DatabaseConnection,
+            load_best_ten_scores, load_leaderboard, ConfigResource, DbResult, DbTask,
+            LeaderboardPage, PlayerScores,
         },
         language_plugin::LanguageResource,
+        storage::{ChallengeScore, PlayerStanding, Storage},
     },
-    view::{despawn_screen, image_handler::ImageMap, GameState},
+    view::{despawn_screen, image_handler::ImageMap, loading_spinner::loading_spinner, GameState},
 };
 
 #[derive(Debug, Component)]
@@ -31,15 +32,59 @@ struct ScoreboardView;
 #[derive(Debug, Component)]
 struct BackFromScoreboardButton;
 
+#[derive(Debug, Component)]
+struct ChallengeScoreList;
+
+#[derive(Debug, Component)]
+struct MultiplayerScoreList;
+
+/// Spinner of the lists of the player's own scores.
+#[derive(Debug, Component)]
+struct ScoreListSpinner;
+
+/// Holds the rows of the leaderboard page that is shown.
+#[derive(Debug, Component)]
+struct LeaderboardRows;
+
+#[derive(Debug, Component)]
+enum LeaderboardText {
+    LevelName,
+    Standing,
+    Page,
+}
+
+#[derive(Debug, Component)]
+enum LeaderboardButton {
+    PrevLevel,
+    NextLevel,
+    PrevPage,
+    NextPage,
+}
+
+/// The leaderboard page on screen, the buttons move from it.
+#[derive(Debug, Default, Resource)]
+struct ShownLeaderboard {
+    prefab_index: i32,
+    page: u32,
+    page_count: u32,
+}
+
 pub struct ScoreboardPlugin;
 
 impl Plugin for ScoreboardPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameState::Scoreboard).with_system(init_view))
+        app.init_resource::<ShownLeaderboard>()
+            .add_system_set(SystemSet::on_enter(GameState::Scoreboard).with_system(init_view))
             .add_system_set(
                 SystemSet::on_exit(GameState::Scoreboard)
                     .with_system(despawn_screen::<ScoreboardView>),
             )
+            .add_system_set(
+                SystemSet::on_update(GameState::Scoreboard)
+                    .with_system(show_scores)
+                    .with_system(show_leaderboard)
+                    .with_system(leaderboard_buttons),
+            )
             .add_system(back_to_main_menu);
     }
 }
@@ -47,21 +92,30 @@ impl Plugin for ScoreboardPlugin {
 fn init_view(
     mut commands: Commands,
     image_handler: Res<ImageMap>,
-    mut db_conn: ResMut<DatabaseConnection>,
+    storage: Option<Res<Storage>>,
     config: Res<ConfigResource>,
     language: Res<LanguageResource>,
+    mut shown_leaderboard: ResMut<ShownLeaderboard>,
 ) {
-    let main_panel = commands
+    // The main menu only opens the scoreboard online and with a player, the
+    // database can still go away in between
+    if let (Some(storage), Some(player)) = (storage.as_ref(), config.selected_player()) {
+        commands.spawn((load_best_ten_scores(storage, player.id), ScoreboardView));
+        commands.spawn((load_leaderboard(storage, 0, 0, player.id), ScoreboardView));
+    }
+    *shown_leaderboard = ShownLeaderboard::default();
+
+    commands
         .spawn(NodeBundle {
             style: Style {
                 position_type: PositionType::Absolute,
                 position: UiRect {
                     top: Val::Percent(10.0),
-                    left: Val::Percent(25.0),
+                    left: Val::Percent(5.0),
                     ..Default::default()
                 },
                 size: Size {
-                    width: Val::Percent(50.0),
+                    width: Val::Percent(90.0),
                     height: Val::Percent(80.0),
                 },
                 flex_direction: FlexDirection::Column,
@@ -92,208 +146,39 @@ fn init_view(
                     ..Default::default()
                 }),
             );
-        })
-        .id();
-
-    // chalenge score panel
-    let first_ten_scores = get_best_ten_challenge_scores_for_player(
-        db_conn.borrow_mut(),
-        config.local_players[config.selected_player_id as usize].id,
-    );
-
-    let challenge_scores = commands
-        .spawn(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                flex_direction: FlexDirection::Column,
-                align_items: AlignItems::FlexStart,
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .with_children(|parent| {
-            parent.spawn(
-                TextBundle::from_section(
-                    language.scoreboard.challenge_scores_title.clone(),
-                    TextStyle {
-                        font: image_handler.2.get(0).unwrap().clone(),
-                        font_size: 40.0,
-                        color: Color::BLACK,
-                    },
-                )
-                .with_style(Style {
-                    margin: UiRect::bottom(Val::Px(20.0)),
-                    ..Default::default()
-                }),
-            );
 
-            for (i, item) in first_ten_scores.iter().enumerate() {
-                let back_color = if i % 2 == 0 {
-                    BackgroundColor(Color::WHITE)
-                } else {
-                    BackgroundColor(Color::GRAY)
-                };
-                parent
-                    .spawn(NodeBundle {
-                        style: Style {
-                            size: Size::new(Val::Percent(35.0), Val::Px(30.0)),
-                            flex_direction: FlexDirection::Row,
-                            position: UiRect::left(Val::Percent(1.0)),
-                            justify_content: JustifyContent::SpaceBetween,
-                            ..Default::default()
-                        },
-                        background_color: back_color,
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                        flex_direction: FlexDirection::Row,
                         ..Default::default()
-                    })
-                    .with_children(|node| {
-                        node.spawn(
-                            TextBundle::from_section(
-                                item.prefab_id.to_string(),
-                                TextStyle {
-                                    font: image_handler.2.get(0).unwrap().clone(),
-                                    font_size: 30.0,
-                                    color: Color::BLACK,
-                                },
-                            )
-                            .with_style(Style {
-                                margin: UiRect::right(Val::Px(30.0)),
-                                ..Default::default()
-                            }),
-                        );
-                        node.spawn(
-                            TextBundle::from_section(
-                                item.level_name.to_string(),
-                                TextStyle {
-                                    font: image_handler.2.get(0).unwrap().clone(),
-                                    font_size: 30.0,
-                                    color: Color::BLACK,
-                                },
-                            )
-                            .with_style(Style {
-                                margin: UiRect::right(Val::Px(30.0)),
-                                ..Default::default()
-                            }),
-                        );
-                        node.spawn(
-                            TextBundle::from_section(
-                                format!("{} steps", item.num_of_steps),
-                                TextStyle {
-                                    font: image_handler.2.get(0).unwrap().clone(),
-                                    font_size: 30.0,
-                                    color: Color::BLACK,
-                                },
-                            )
-                            .with_style(Style {
-                                margin: UiRect::right(Val::Px(30.0)),
-                                ..Default::default()
-                            }),
-                        );
-                    });
-            }
-        })
-        .id();
-
-    commands.entity(main_panel).add_child(challenge_scores);
-
-    // Multiplayer scores
-    let first_ten_mul_scores = get_best_ten_multiplayer_scores_for_player(
-        db_conn.borrow_mut(),
-        config.local_players[config.selected_player_id as usize].id,
-    );
-
-    let multiplayer_scores = commands
-        .spawn(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                flex_direction: FlexDirection::Column,
-                align_items: AlignItems::FlexStart,
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .with_children(|parent| {
-            parent.spawn(
-                TextBundle::from_section(
-                    language.scoreboard.multiplayer_scores_title.clone(),
-                    TextStyle {
-                        font: image_handler.2.get(0).unwrap().clone(),
-                        font_size: 40.0,
-                        color: Color::BLACK,
                     },
-                )
-                .with_style(Style {
-                    margin: UiRect::bottom(Val::Px(20.0)),
                     ..Default::default()
-                }),
-            );
+                })
+                .with_children(|columns| {
+                    // Challenge scores
+                    score_list(
+                        columns,
+                        &image_handler,
+                        &language,
+                        language.scoreboard.challenge_scores_title.clone(),
+                        ChallengeScoreList,
+                    );
 
-            for (i, item) in first_ten_mul_scores.iter().enumerate() {
-                let back_color = if i % 2 == 0 {
-                    BackgroundColor(Color::WHITE)
-                } else {
-                    BackgroundColor(Color::GRAY)
-                };
-                parent
-                    .spawn(NodeBundle {
-                        style: Style {
-                            size: Size::new(Val::Percent(35.0), Val::Px(30.0)),
-                            flex_direction: FlexDirection::Row,
-                            position: UiRect::left(Val::Percent(1.0)),
-                            justify_content: JustifyContent::SpaceBetween,
-                            ..Default::default()
-                        },
-                        background_color: back_color,
-                        ..Default::default()
-                    })
-                    .with_children(|node| {
-                        node.spawn(
-                            TextBundle::from_section(
-                                item.prefab_id.to_string(),
-                                TextStyle {
-                                    font: image_handler.2.get(0).unwrap().clone(),
-                                    font_size: 30.0,
-                                    color: Color::BLACK,
-                                },
-                            )
-                            .with_style(Style {
-                                margin: UiRect::right(Val::Px(30.0)),
-                                ..Default::default()
-                            }),
-                        );
-                        node.spawn(
-                            TextBundle::from_section(
-                                item.level_name.to_string(),
-                                TextStyle {
-                                    font: image_handler.2.get(0).unwrap().clone(),
-                                    font_size: 30.0,
-                                    color: Color::BLACK,
-                                },
-                            )
-                            .with_style(Style {
-                                margin: UiRect::right(Val::Px(30.0)),
-                                ..Default::default()
-                            }),
-                        );
-                        node.spawn(
-                            TextBundle::from_section(
-                                format!("{} steps", item.num_of_steps),
-                                TextStyle {
-                                    font: image_handler.2.get(0).unwrap().clone(),
-                                    font_size: 30.0,
-                                    color: Color::BLACK,
-                                },
-                            )
-                            .with_style(Style {
-                                margin: UiRect::right(Val::Px(30.0)),
-                                ..Default::default()
-                            }),
-                        );
-                    });
-            }
-        })
-        .id();
+                    // Multiplayer scores
+                    score_list(
+                        columns,
+                        &image_handler,
+                        &language,
+                        language.scoreboard.multiplayer_scores_title.clone(),
+                        MultiplayerScoreList,
+                    );
 
-    commands.entity(main_panel).add_child(multiplayer_scores);
+                    // Leaderboard of one prefab across all players
+                    leaderboard(columns, &image_handler, &language);
+                });
+        });
 
     commands
         .spawn(ButtonBundle {
@@ -330,6 +215,377 @@ fn init_view(
         .insert(BackFromScoreboardButton);
 }
 
+fn column_title(parent: &mut ChildBuilder, image_handler: &ImageMap, title: String) {
+    parent.spawn(
+        TextBundle::from_section(
+            title,
+            TextStyle {
+                font: image_handler.2.get(0).unwrap().clone(),
+                font_size: 40.0,
+                color: Color::BLACK,
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::bottom(Val::Px(20.0)),
+            ..Default::default()
+        }),
+    );
+}
+
+fn column_text(
+    image_handler: &ImageMap,
+    text: impl Into<String>,
+    marker: LeaderboardText,
+) -> (TextBundle, LeaderboardText) {
+    (
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: image_handler.2.get(0).unwrap().clone(),
+                font_size: 30.0,
+                color: Color::BLACK,
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::all(Val::Px(5.0)),
+            ..Default::default()
+        }),
+        marker,
+    )
+}
+
+/// A titled column the rows of scores are added to when they arrive.
+fn score_list(
+    parent: &mut ChildBuilder,
+    image_handler: &ImageMap,
+    language: &LanguageResource,
+    title: String,
+    marker: impl Component,
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(33.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(marker)
+        .with_children(|parent| {
+            column_title(parent, image_handler, title);
+            parent
+                .spawn(loading_spinner(
+                    image_handler,
+                    language,
+                    Style {
+                        position: UiRect::left(Val::Percent(1.0)),
+                        ..Default::default()
+                    },
+                ))
+                .insert(ScoreListSpinner);
+        });
+}
+
+fn leaderboard(parent: &mut ChildBuilder, image_handler: &ImageMap, language: &LanguageResource) {
+    let switch_button = |image: usize| ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(40.0), Val::Px(40.0)),
+            margin: UiRect::all(Val::Px(5.0)),
+            ..Default::default()
+        },
+        image: image_handler.1.get(image).unwrap().clone(),
+        ..Default::default()
+    };
+    let switch_row = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Px(50.0)),
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceBetween,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(34.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            column_title(
+                parent,
+                image_handler,
+                language.scoreboard.leaderboard_title.clone(),
+            );
+            parent.spawn(switch_row.clone()).with_children(|node| {
+                node.spawn(switch_button(7))
+                    .insert(LeaderboardButton::PrevLevel);
+                node.spawn(column_text(image_handler, "", LeaderboardText::LevelName));
+                node.spawn(switch_button(8))
+                    .insert(LeaderboardButton::NextLevel);
+            });
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Auto),
+                        flex_direction: FlexDirection::Column,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(LeaderboardRows)
+                .with_children(|rows| {
+                    rows.spawn(loading_spinner(image_handler, language, Style::default()));
+                });
+            parent.spawn(column_text(image_handler, "", LeaderboardText::Standing));
+            parent.spawn(switch_row).with_children(|node| {
+                node.spawn(switch_button(7))
+                    .insert(LeaderboardButton::PrevPage);
+                node.spawn(column_text(image_handler, "", LeaderboardText::Page));
+                node.spawn(switch_button(8))
+                    .insert(LeaderboardButton::NextPage);
+            });
+        });
+}
+
+fn show_scores(
+    mut commands: Commands,
+    mut results: EventReader<DbResult<PlayerScores>>,
+    challenge_list: Query<Entity, With<ChallengeScoreList>>,
+    multiplayer_list: Query<Entity, With<MultiplayerScoreList>>,
+    spinners: Query<Entity, With<ScoreListSpinner>>,
+    image_handler: Res<ImageMap>,
+) {
+    for DbResult(scores) in results.iter() {
+        for spinner in &spinners {
+            commands.entity(spinner).despawn_recursive();
+        }
+        for list in &challenge_list {
+            commands.entity(list).with_children(|parent| {
+                for (i, item) in scores.challenge.iter().enumerate() {
+                    spawn_score_row(parent, &image_handler, i, item);
+                }
+            });
+        }
+        for list in &multiplayer_list {
+            commands.entity(list).with_children(|parent| {
+                for (i, item) in scores.multiplayer.iter().enumerate() {
+                    spawn_score_row(parent, &image_handler, i, item);
+                }
+            });
+        }
+    }
+}
+
+fn spawn_score_row(
+    parent: &mut ChildBuilder,
+    image_handler: &ImageMap,
+    i: usize,
+    item: &ChallengeScore,
+) {
+    spawn_row(
+        parent,
+        image_handler,
+        row_color(i),
+        [
+            item.prefab_id.to_string(),
+            item.level_name.to_string(),
+            format!("{} steps", item.num_of_steps),
+        ],
+    );
+}
+
+fn show_leaderboard(
+    mut commands: Commands,
+    mut results: EventReader<DbResult<LeaderboardPage>>,
+    mut shown_leaderboard: ResMut<ShownLeaderboard>,
+    rows: Query<Entity, With<LeaderboardRows>>,
+    mut texts: Query<(&mut Text, &LeaderboardText)>,
+    config: Res<ConfigResource>,
+    language: Res<LanguageResource>,
+    image_handler: Res<ImageMap>,
+) {
+    let player_id = config.selected_player().map(|player| player.id);
+    for DbResult(page) in results.iter() {
+        *shown_leaderboard = ShownLeaderboard {
+            prefab_index: page.prefab_index,
+            page: page.page,
+            page_count: page.page_count,
+        };
+        for (mut text, kind) in &mut texts {
+            text.sections[0].value = match kind {
+                LeaderboardText::LevelName => page.level_name.clone(),
+                LeaderboardText::Standing => standing_text(page.standing, &language),
+                LeaderboardText::Page => format!(
+                    "{} {}/{}",
+                    language.scoreboard.page_label,
+                    page.page + 1,
+                    page.page_count
+                ),
+            };
+        }
+        for rows in &rows {
+            commands
+                .entity(rows)
+                .despawn_descendants()
+                .with_children(|parent| {
+                    for (i, entry) in page.entries.iter().enumerate() {
+                        let back_color = if Some(entry.player_id) == player_id {
+                            BackgroundColor(Color::AQUAMARINE)
+                        } else {
+                            row_color(i)
+                        };
+                        // Players with the same steps share the rank
+                        spawn_row(
+                            parent,
+                            &image_handler,
+                            back_color,
+                            [
+                                format!("{}.", entry.rank),
+                                entry.player_name.clone(),
+                                format!("{} steps", entry.num_of_steps),
+                            ],
+                        );
+                    }
+                });
+        }
+    }
+}
+
+fn standing_text(standing: Option<PlayerStanding>, language: &LanguageResource) -> String {
+    match standing {
+        Some(standing) => format!(
+            "{} {}, {} {} steps",
+            language.scoreboard.your_rank_label,
+            standing.rank,
+            language.scoreboard.personal_best_label,
+            standing.personal_best
+        ),
+        None => language.scoreboard.not_played_label.clone(),
+    }
+}
+
+fn leaderboard_buttons(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &LeaderboardButton, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+    pending: Query<(), With<DbTask<LeaderboardPage>>>,
+    rows: Query<Entity, With<LeaderboardRows>>,
+    shown_leaderboard: Res<ShownLeaderboard>,
+    storage: Option<Res<Storage>>,
+    config: Res<ConfigResource>,
+    image_handler: Res<ImageMap>,
+    language: Res<LanguageResource>,
+) {
+    for (interaction, button, mut back_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *back_color = BackgroundColor(Color::YELLOW);
+                let storage = match storage.as_ref() {
+                    Some(storage) if pending.is_empty() => storage,
+                    _ => continue,
+                };
+                let ShownLeaderboard {
+                    prefab_index,
+                    page,
+                    page_count,
+                } = *shown_leaderboard;
+                let (prefab_index, page) = match button {
+                    LeaderboardButton::PrevLevel => (prefab_index - 1, 0),
+                    LeaderboardButton::NextLevel => (prefab_index + 1, 0),
+                    LeaderboardButton::PrevPage if page > 0 => (prefab_index, page - 1),
+                    LeaderboardButton::NextPage if page + 1 < page_count => {
+                        (prefab_index, page + 1)
+                    }
+                    // Already on the first or the last page
+                    _ => continue,
+                };
+                let player_id = match config.selected_player() {
+                    Some(player) => player.id,
+                    None => continue,
+                };
+                commands.spawn((
+                    load_leaderboard(storage, prefab_index, page, player_id),
+                    ScoreboardView,
+                ));
+                for rows in &rows {
+                    commands
+                        .entity(rows)
+                        .despawn_descendants()
+                        .with_children(|rows| {
+                            rows.spawn(loading_spinner(
+                                &image_handler,
+                                &language,
+                                Style::default(),
+                            ));
+                        });
+                }
+            }
+            Interaction::Hovered => {
+                *back_color = BackgroundColor(Color::AQUAMARINE);
+            }
+            Interaction::None => {
+                *back_color = BackgroundColor(Color::BEIGE);
+            }
+        }
+    }
+}
+
+fn row_color(i: usize) -> BackgroundColor {
+    if i % 2 == 0 {
+        BackgroundColor(Color::WHITE)
+    } else {
+        BackgroundColor(Color::GRAY)
+    }
+}
+
+fn spawn_row(
+    parent: &mut ChildBuilder,
+    image_handler: &ImageMap,
+    back_color: BackgroundColor,
+    values: [String; 3],
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(90.0), Val::Px(30.0)),
+                flex_direction: FlexDirection::Row,
+                position: UiRect::left(Val::Percent(1.0)),
+                justify_content: JustifyContent::SpaceBetween,
+                ..Default::default()
+            },
+            background_color: back_color,
+            ..Default::default()
+        })
+        .with_children(|node| {
+            for value in values {
+                node.spawn(
+                    TextBundle::from_section(
+                        value,
+                        TextStyle {
+                            font: image_handler.2.get(0).unwrap().clone(),
+                            font_size: 30.0,
+                            color: Color::BLACK,
+                        },
+                    )
+                    .with_style(Style {
+                        margin: UiRect::right(Val::Px(30.0)),
+                        ..Default::default()
+                    }),
+                );
+            }
+        });
+}
+
 fn back_to_main_menu(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),